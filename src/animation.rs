@@ -0,0 +1,257 @@
+//! Sampling of Heck point definitions, see [sample]
+
+use std::f64::consts::PI;
+use crate::types::heck::{HeckEasing, Modifier, Operation, Point, PointValue};
+
+/// What a point definition describes. Decides how many values a point has and how they are interpolated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointKind {
+    /// A single number, e.g. `dissolve` or `time`
+    Vec1,
+    /// e.g. `position` or `scale`
+    Vec3,
+    /// A color, RGBA
+    Vec4,
+    /// Euler angles in degrees, e.g. `rotation`. Sampled as a quaternion, `[x, y, z, w]`
+    Quaternion,
+}
+
+impl HeckEasing {
+    /// Maps a progress from 0 to 1 onto the eased progress
+    pub fn apply(&self, p: f64) -> f64 {
+        use HeckEasing::*;
+        match self {
+            Linear => p,
+            Step => p.floor(),
+            InQuad => p * p,
+            OutQuad => -(p * (p - 2.0)),
+            InOutQuad => if p < 0.5 { 2.0 * p * p } else { -2.0 * p * p + 4.0 * p - 1.0 },
+            InCubic => p.powi(3),
+            OutCubic => (p - 1.0).powi(3) + 1.0,
+            InOutCubic => if p < 0.5 { 4.0 * p.powi(3) } else { 0.5 * (2.0 * p - 2.0).powi(3) + 1.0 },
+            InQuart => p.powi(4),
+            OutQuart => (p - 1.0).powi(3) * (1.0 - p) + 1.0,
+            InOutQuart => if p < 0.5 { 8.0 * p.powi(4) } else { -8.0 * (p - 1.0).powi(4) + 1.0 },
+            InQuint => p.powi(5),
+            OutQuint => (p - 1.0).powi(5) + 1.0,
+            InOutQuint => if p < 0.5 { 16.0 * p.powi(5) } else { 0.5 * (2.0 * p - 2.0).powi(5) + 1.0 },
+            InSine => ((p - 1.0) * PI / 2.0).sin() + 1.0,
+            OutSine => (p * PI / 2.0).sin(),
+            InOutSine => 0.5 * (1.0 - (p * PI).cos()),
+            InCirc => 1.0 - (1.0 - p * p).sqrt(),
+            OutCirc => ((2.0 - p) * p).sqrt(),
+            InOutCirc => if p < 0.5 {
+                0.5 * (1.0 - (1.0 - 4.0 * p * p).sqrt())
+            } else {
+                0.5 * ((-(2.0 * p - 3.0) * (2.0 * p - 1.0)).sqrt() + 1.0)
+            },
+            InExpo => if p == 0.0 { 0.0 } else { 2f64.powf(10.0 * (p - 1.0)) },
+            OutExpo => if p == 1.0 { 1.0 } else { 1.0 - 2f64.powf(-10.0 * p) },
+            InOutExpo => if p == 0.0 || p == 1.0 {
+                p
+            } else if p < 0.5 {
+                0.5 * 2f64.powf(20.0 * p - 10.0)
+            } else {
+                -0.5 * 2f64.powf(-20.0 * p + 10.0) + 1.0
+            },
+            InBack => p.powi(3) - p * (p * PI).sin(),
+            OutBack => {
+                let f = 1.0 - p;
+                1.0 - (f.powi(3) - f * (f * PI).sin())
+            },
+            InOutBack => if p < 0.5 {
+                let f = 2.0 * p;
+                0.5 * (f.powi(3) - f * (f * PI).sin())
+            } else {
+                let f = 1.0 - (2.0 * p - 1.0);
+                0.5 * (1.0 - (f.powi(3) - f * (f * PI).sin())) + 0.5
+            },
+            InElastic => (13.0 * PI / 2.0 * p).sin() * 2f64.powf(10.0 * (p - 1.0)),
+            OutElastic => (-13.0 * PI / 2.0 * (p + 1.0)).sin() * 2f64.powf(-10.0 * p) + 1.0,
+            InOutElastic => if p < 0.5 {
+                0.5 * (13.0 * PI / 2.0 * (2.0 * p)).sin() * 2f64.powf(10.0 * (2.0 * p - 1.0))
+            } else {
+                0.5 * ((-13.0 * PI / 2.0 * (2.0 * p)).sin() * 2f64.powf(-10.0 * (2.0 * p - 1.0)) + 2.0)
+            },
+            InBounce => 1.0 - OutBounce.apply(1.0 - p),
+            OutBounce => if p < 4.0 / 11.0 {
+                121.0 * p * p / 16.0
+            } else if p < 8.0 / 11.0 {
+                363.0 / 40.0 * p * p - 99.0 / 10.0 * p + 17.0 / 5.0
+            } else if p < 9.0 / 10.0 {
+                4356.0 / 361.0 * p * p - 35442.0 / 1805.0 * p + 16061.0 / 1805.0
+            } else {
+                54.0 / 5.0 * p * p - 513.0 / 25.0 * p + 268.0 / 25.0
+            },
+            InOutBounce => if p < 0.5 {
+                0.5 * InBounce.apply(p * 2.0)
+            } else {
+                0.5 * OutBounce.apply(p * 2.0 - 1.0) + 0.5
+            },
+        }
+    }
+}
+
+/// Samples the points at time `t` (normalized, 0 to 1). Returns `None` if a point depends on base game values or the points have too few values to interpolate
+pub fn sample(points: &[Point], t: f64, kind: PointKind) -> Option<Vec<f64>> {
+    sample_with_bases(points, t, kind, |_| None)
+}
+
+/// Like [sample], but base game values (`baseHeadPosition`, `baseNote0Color` etc.) are looked up with `bases`
+pub fn sample_with_bases(points: &[Point], t: f64, kind: PointKind, bases: impl Fn(&str) -> Option<Vec<f64>>) -> Option<Vec<f64>> {
+    let values = |point: &Point| point_values(point, &bases);
+    let finish = |values: Vec<f64>| if kind == PointKind::Quaternion { quaternion_from_euler(&values) } else { values };
+
+    let first = points.first()?;
+    let last = points.last()?;
+    if points.len() == 1 || t <= first.time {
+        return values(first).map(finish);
+    }
+    if t >= last.time {
+        return values(last).map(finish);
+    }
+
+    let right = points.iter().position(|x| x.time > t)?;
+    let left = right - 1;
+    let (left_point, right_point) = (&points[left], &points[right]);
+    let span = right_point.time - left_point.time;
+    let progress = if span > 0.0 { (t - left_point.time) / span } else { 1.0 };
+    let progress = right_point.easing.map(|x| x.apply(progress)).unwrap_or(progress);
+
+    let a = values(left_point)?;
+    let b = values(right_point)?;
+    Some(match kind {
+        PointKind::Quaternion => slerp(&quaternion_from_euler(&a), &quaternion_from_euler(&b), progress),
+        PointKind::Vec3 if right_point.spline => {
+            let before = values(&points[left.saturating_sub(1)])?;
+            let after = values(&points[(right + 1).min(points.len() - 1)])?;
+            catmull_rom(&before, &a, &b, &after, progress)?
+        },
+        PointKind::Vec4 if right_point.hsv_lerp => lerp_hsv(&a, &b, progress)?,
+        _ => lerp(&a, &b, progress),
+    })
+}
+
+fn point_values(point: &Point, bases: &impl Fn(&str) -> Option<Vec<f64>>) -> Option<Vec<f64>> {
+    let values = resolve_values(&point.values, bases)?;
+    point.modifiers.iter().try_fold(values, |values, modifier| apply_modifier(values, modifier, bases))
+}
+
+fn resolve_values(values: &[PointValue], bases: &impl Fn(&str) -> Option<Vec<f64>>) -> Option<Vec<f64>> {
+    let mut result = vec![];
+    for value in values {
+        match value {
+            PointValue::Number(value) => result.push(*value),
+            PointValue::Base(name) => result.extend(bases(name)?),
+        }
+    }
+    Some(result)
+}
+
+fn apply_modifier(values: Vec<f64>, modifier: &Modifier, bases: &impl Fn(&str) -> Option<Vec<f64>>) -> Option<Vec<f64>> {
+    let operand = resolve_values(&modifier.values, bases)?;
+    let operand = modifier.modifiers.iter().try_fold(operand, |operand, modifier| apply_modifier(operand, modifier, bases))?;
+    let operand_at = |i: usize| if operand.len() == 1 { operand[0] } else { operand.get(i).copied().unwrap_or(0.0) };
+    Some(values.iter().enumerate().map(|(i, value)| match modifier.operation {
+        Operation::None => operand_at(i),
+        Operation::Add => value + operand_at(i),
+        Operation::Sub => value - operand_at(i),
+        Operation::Mul => value * operand_at(i),
+        Operation::Div => value / operand_at(i),
+    }).collect())
+}
+
+fn lerp(a: &[f64], b: &[f64], t: f64) -> Vec<f64> {
+    a.iter().zip(b).map(|(a, b)| a + (b - a) * t).collect()
+}
+
+/// `None` if the points don't have the same number of values
+fn catmull_rom(p0: &[f64], p1: &[f64], p2: &[f64], p3: &[f64], t: f64) -> Option<Vec<f64>> {
+    if [p0, p2, p3].iter().any(|x| x.len() != p1.len()) {
+        return None;
+    }
+    let (tt, ttt) = (t * t, t * t * t);
+    let q0 = -ttt + 2.0 * tt - t;
+    let q1 = 3.0 * ttt - 5.0 * tt + 2.0;
+    let q2 = -3.0 * ttt + 4.0 * tt + t;
+    let q3 = ttt - tt;
+    Some((0..p1.len()).map(|i| 0.5 * (p0[i] * q0 + p1[i] * q1 + p2[i] * q2 + p3[i] * q3)).collect())
+}
+
+fn rgb_to_hsv(r: f64, g: f64, b: f64) -> [f64; 3] {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        ((g - b) / delta).rem_euclid(6.0) / 6.0
+    } else if max == g {
+        ((b - r) / delta + 2.0) / 6.0
+    } else {
+        ((r - g) / delta + 4.0) / 6.0
+    };
+    [hue, if max == 0.0 { 0.0 } else { delta / max }, max]
+}
+
+fn hsv_to_rgb(h: f64, s: f64, v: f64) -> [f64; 3] {
+    let h = h.rem_euclid(1.0) * 6.0;
+    let c = v * s;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let [r, g, b] = match h as i32 {
+        0 => [c, x, 0.0],
+        1 => [x, c, 0.0],
+        2 => [0.0, c, x],
+        3 => [0.0, x, c],
+        4 => [x, 0.0, c],
+        _ => [c, 0.0, x],
+    };
+    [r + v - c, g + v - c, b + v - c]
+}
+
+/// `None` if either color has less than 3 values
+fn lerp_hsv(a: &[f64], b: &[f64], t: f64) -> Option<Vec<f64>> {
+    if a.len() < 3 || b.len() < 3 {
+        return None;
+    }
+    let from = rgb_to_hsv(a[0], a[1], a[2]);
+    let to = rgb_to_hsv(b[0], b[1], b[2]);
+    let hsv = lerp(&from, &to, t);
+    let mut result = hsv_to_rgb(hsv[0], hsv[1], hsv[2]).to_vec();
+    result.extend(lerp(&a[3..], &b[3..], t));
+    Some(result)
+}
+
+/// Converts Unity euler angles (degrees, applied Z, then X, then Y) into a quaternion, `[x, y, z, w]`
+pub fn quaternion_from_euler(euler: &[f64]) -> Vec<f64> {
+    let half = |i: usize| euler.get(i).copied().unwrap_or(0.0).to_radians() / 2.0;
+    let (sx, cx) = half(0).sin_cos();
+    let (sy, cy) = half(1).sin_cos();
+    let (sz, cz) = half(2).sin_cos();
+    vec![
+        cy * sx * cz + sy * cx * sz,
+        sy * cx * cz - cy * sx * sz,
+        cy * cx * sz - sy * sx * cz,
+        cy * cx * cz + sy * sx * sz,
+    ]
+}
+
+fn slerp(a: &[f64], b: &[f64], t: f64) -> Vec<f64> {
+    let mut dot: f64 = a.iter().zip(b).map(|(a, b)| a * b).sum();
+    let b: Vec<f64> = if dot < 0.0 {
+        dot = -dot;
+        b.iter().map(|x| -x).collect()
+    } else {
+        b.to_vec()
+    };
+    if dot > 0.9995 {
+        let result = lerp(a, &b, t);
+        let length = result.iter().map(|x| x * x).sum::<f64>().sqrt();
+        return result.iter().map(|x| x / length).collect();
+    }
+    let theta = dot.acos();
+    let sin_theta = theta.sin();
+    let wa = ((1.0 - t) * theta).sin() / sin_theta;
+    let wb = (t * theta).sin() / sin_theta;
+    a.iter().zip(&b).map(|(a, b)| a * wa + b * wb).collect()
+}
@@ -0,0 +1,104 @@
+//! Which lights and events each environment has, see [Environments]
+
+use std::collections::HashMap;
+use crate::error::EnvironmentWarning;
+use crate::types::common::BasicEventType;
+use crate::types::primary::Event;
+use crate::Beatmap;
+
+/// A group of lights targeted by [LightEventBox](crate::types::lightning::LightEventBox)
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightGroup {
+    pub id: i32,
+    pub light_count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Environment {
+    pub name: String,
+    /// Empty for environments that are only lit by basic events
+    pub light_groups: Vec<LightGroup>,
+    /// Basic event types the environment reacts to. Rotation and BPM events are always allowed
+    pub event_types: Vec<BasicEventType>,
+}
+
+impl Environment {
+    pub fn light_group(&self, id: i32) -> Option<&LightGroup> {
+        self.light_groups.iter().find(|x| x.id == id)
+    }
+
+    pub fn supports(&self, kind: BasicEventType) -> bool {
+        use BasicEventType::*;
+        matches!(kind, EarlyRotation | LateRotation | BpmChange) || self.event_types.contains(&kind)
+    }
+
+    /// Lists basic events and light event boxes that target something the environment doesn't have
+    pub fn check(&self, beatmap: &Beatmap) -> Vec<EnvironmentWarning> {
+        let mut warnings = vec![];
+        for event in &beatmap.events {
+            match event {
+                Event::BasicEvent(event) if !event.event_type().map(|x| self.supports(x)).unwrap_or(false) => {
+                    warnings.push(EnvironmentWarning::UnsupportedEventType { beat: event.beat, kind: event.kind, environment: self.name.clone() });
+                },
+                Event::ColorBoost(event) if !self.supports(BasicEventType::ColorBoost) => {
+                    warnings.push(EnvironmentWarning::UnsupportedEventType { beat: event.beat, kind: BasicEventType::ColorBoost.into(), environment: self.name.clone() });
+                },
+                Event::LightEventBox(event_box) if self.light_group(event_box.group).is_none() => {
+                    warnings.push(EnvironmentWarning::MissingLightGroup { beat: event_box.beat, group: event_box.group, environment: self.name.clone() });
+                },
+                _ => {}
+            }
+        }
+        warnings
+    }
+}
+
+/// A registry of environments, keyed by name (as in [BeatmapSetMeta::environment_name](crate::BeatmapSetMeta))
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Environments {
+    pub environments: HashMap<String, Environment>,
+}
+
+const CLASSIC_EVENTS: [BasicEventType; 10] = {
+    use BasicEventType::*;
+    [BackLasers, RingLights, LeftLasers, RightLasers, CenterLights, ColorBoost, RingSpin, RingZoom, LeftLaserSpeed, RightLaserSpeed]
+};
+
+const CLASSIC_ENVIRONMENTS: [&str; 19] = [
+    "DefaultEnvironment", "TriangleEnvironment", "NiceEnvironment", "BigMirrorEnvironment", "DragonsEnvironment",
+    "KDAEnvironment", "MonstercatEnvironment", "CrabRaveEnvironment", "PanicEnvironment", "RocketEnvironment",
+    "GreenDayEnvironment", "GreenDayGrenadeEnvironment", "TimbalandEnvironment", "FitBeatEnvironment", "LinkinParkEnvironment",
+    "BTSEnvironment", "KaleidoscopeEnvironment", "OriginsEnvironment", "HalloweenEnvironment",
+];
+
+impl Environments {
+    /// The base game environments that are lit by basic events, Billie included.
+    /// Environments lit by light event boxes (Weave and later) are left out rather than guessed: their group IDs and light counts come from the game data, so add them with [Environments::insert]
+    pub fn builtin() -> Self {
+        use BasicEventType::*;
+        let mut environments = Self::default();
+        for name in CLASSIC_ENVIRONMENTS {
+            environments.insert(Environment { name: name.to_string(), light_groups: vec![], event_types: CLASSIC_EVENTS.to_vec() });
+        }
+        let with_extra = |name: &str, extra: &[BasicEventType]| Environment {
+            name: name.to_string(),
+            light_groups: vec![],
+            event_types: CLASSIC_EVENTS.iter().chain(extra).copied().collect(),
+        };
+        environments.insert(with_extra("GlassDesertEnvironment", &[]));
+        environments.insert(with_extra("SkrillexEnvironment", &[ExtraLeftLights, ExtraRightLights, ExtraLeftLasers, ExtraRightLasers]));
+        environments.insert(with_extra("BillieEnvironment", &[ExtraLeftLights, ExtraRightLights, ExtraLeftLasers, ExtraRightLasers]));
+        environments.insert(with_extra("InterscopeEnvironment", &[ExtraLeftLights, ExtraRightLights, UtilityEvent0, UtilityEvent1, UtilityEvent2, UtilityEvent3]));
+        environments.insert(with_extra("GagaEnvironment", &[ExtraLeftLights, ExtraRightLights, ExtraLeftLasers, ExtraRightLasers, UtilityEvent0, UtilityEvent1, UtilityEvent2, UtilityEvent3]));
+        environments
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Environment> {
+        self.environments.get(name)
+    }
+
+    /// Adds an environment, replacing the one with the same name
+    pub fn insert(&mut self, environment: Environment) {
+        self.environments.insert(environment.name.clone(), environment);
+    }
+}
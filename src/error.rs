@@ -1,6 +1,7 @@
 use std::fmt::Debug;
 use std::io;
 use thiserror::Error;
+use crate::types::common::NoteColor;
 
 pub type Result<T> = core::result::Result<T, Error>;
 
@@ -8,13 +9,89 @@ pub type Result<T> = core::result::Result<T, Error>;
 pub enum Error {
     #[error("Failed to parse info.dat file.")]
     InfoParsingFailed(#[from] serde_json::Error),
-    #[error("Failed to parse a beatmap file. (new format: {err_as_new}, old format: {err_as_old})")]
+    #[error("Failed to parse a beatmap file. (new format: {err_as_new}, old format: {err_as_old}, v4 format: {err_as_v4})")]
     BeatmapParsingFailed {
         err_as_new: serde_json::Error,
-        err_as_old: serde_json::Error
+        err_as_old: serde_json::Error,
+        err_as_v4: serde_json::Error
     },
+    #[error("Failed to parse an audio data file.")]
+    AudioDataParsingFailed(#[source] serde_json::Error),
+    #[error("Failed to parse a lightshow file.")]
+    LightshowParsingFailed(#[source] serde_json::Error),
+    #[error("Failed to parse difficulty file {filename}.")]
+    DifficultyParsingFailed {
+        filename: String,
+        #[source]
+        source: Box<Error>
+    },
+    #[error("Failed to parse custom data ({0}).")]
+    CustomDataParsingFailed(String),
+    #[error("File {0} is missing from the level.")]
+    MissingFile(String),
+    #[error("Difficulty {0} has lighting but no lightshow file to keep it in.")]
+    MissingLightshow(String),
+    #[error(transparent)]
+    ZipError(#[from] zip::result::ZipError),
+    #[error("Failed to serialize a file.")]
+    SerializationFailed(#[source] serde_json::Error),
     #[error(transparent)]
     IOError(#[from] io::Error)
 }
 
+/// Something that was lost or altered when converting a beatmap into another format
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ConversionWarning {
+    #[error("Angle offset {angle_offset} of the note at beat {beat} was dropped.")]
+    AngleOffsetDropped { beat: f64, angle_offset: f64 },
+    #[error("Obstacle at beat {beat} was reshaped to a full or crouch wall.")]
+    ObstacleReshaped { beat: f64 },
+    #[error("Rotation {value} at beat {beat} was rounded to {rounded_to}.")]
+    RotationRounded { beat: f64, value: f64, rounded_to: f64 },
+    #[error("Rotation {value} at beat {beat} was written in the Mapping Extensions encoding, which the unmodded game misreads.")]
+    RotationNeedsMappingExtensions { beat: f64, value: f64 },
+    #[error("Burst slider at beat {beat} was dropped.")]
+    BurstSliderDropped { beat: f64 },
+    #[error("Light event box at beat {beat} was dropped.")]
+    LightEventBoxDropped { beat: f64 },
+    #[error("Basic event types with keywords were dropped.")]
+    KeywordsDropped,
+    #[error("Custom data key {key} of the object at beat {beat} has no name in the other version and was kept as is.")]
+    CustomDataKept { beat: f64, key: String },
+    #[error("Custom data key {key} of the beatmap has no name in the other version and was kept as is.")]
+    BeatmapCustomDataKept { key: String },
+    #[error("Property {property} of the custom event at beat {beat} has no name in the other version and was kept as is.")]
+    CustomEventPropertyKept { beat: f64, property: String },
+    #[error("BPM event at beat {beat} was dropped, v4 keeps them in the audio file.")]
+    BpmEventDropped { beat: f64 },
+    #[error("Info has no audio data file, which v4 needs for BPM changes. The filename was left empty.")]
+    AudioDataMissing,
+    #[error("Info has no song duration, which v4 needs. It was set to 0.")]
+    SongDurationMissing,
+    #[error("Difficulty {filename} has no lightshow file, which v4 needs. The filename was left empty.")]
+    LightshowFilenameMissing { filename: String },
+    #[error("Difficulty {filename} has no color scheme, so v4 gives it the info's first one.")]
+    ColorSchemeDefaulted { filename: String },
+}
+
+/// An event that the chosen environment has nothing to show for
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum EnvironmentWarning {
+    #[error("Event at beat {beat} has type {kind}, which {environment} doesn't use.")]
+    UnsupportedEventType { beat: f64, kind: i32, environment: String },
+    #[error("Light event box at beat {beat} targets group {group}, which {environment} doesn't have.")]
+    MissingLightGroup { beat: f64, group: i32, environment: String },
+}
 
+/// A spot where a saber can't simply alternate between forehand and backhand swings
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ParityWarning {
+    #[error("{color:?} swing at beat {beat} repeats the parity of the previous one with no time to reset.")]
+    ParityBreak { beat: f64, color: NoteColor },
+    #[error("{color:?} swing at beat {beat} repeats the direction of the previous one with no time to reset.")]
+    DoubleDirectional { beat: f64, color: NoteColor },
+    #[error("{color:?} saber has to reset before the swing at beat {beat}.")]
+    Reset { beat: f64, color: NoteColor },
+    #[error("Bombs make the {color:?} saber reset before the swing at beat {beat}.")]
+    BombReset { beat: f64, color: NoteColor },
+}
@@ -0,0 +1,68 @@
+//! Note jump calculations, following the game. Durations are in beats unless told otherwise, distances in meters
+
+use crate::types::primary::BeatmapMeta;
+
+/// The game halves the default jump duration until notes travel less than this far, in meters, before reaching the player
+pub const MAX_HALF_JUMP_DISTANCE: f64 = 17.999;
+/// Shortest half jump duration, in beats, whatever the offset
+pub const MIN_HALF_JUMP_DURATION: f64 = 0.25;
+
+/// Half jump duration before the offset is added: 4 beats, halved until it fits [MAX_HALF_JUMP_DISTANCE]
+fn base_half_jump_duration(njs: f64, bpm: f64) -> f64 {
+    let seconds_per_beat = 60.0 / bpm;
+    let mut duration = 4.0;
+    while njs * seconds_per_beat * duration > MAX_HALF_JUMP_DISTANCE {
+        duration /= 2.0;
+    }
+    duration
+}
+
+/// Beats a note takes from spawning to reaching the player, at `njs` (note jump speed) with `offset` (note jump start beat offset)
+pub fn half_jump_duration(njs: f64, offset: f64, bpm: f64) -> f64 {
+    (base_half_jump_duration(njs, bpm) + offset).max(MIN_HALF_JUMP_DURATION)
+}
+
+/// Distance between where notes spawn and where they disappear behind the player
+pub fn jump_distance(njs: f64, offset: f64, bpm: f64) -> f64 {
+    njs * 60.0 / bpm * half_jump_duration(njs, offset, bpm) * 2.0
+}
+
+/// Milliseconds between a note spawning and reaching the player
+pub fn reaction_time(njs: f64, offset: f64, bpm: f64) -> f64 {
+    half_jump_duration(njs, offset, bpm) * 60.0 / bpm * 1000.0
+}
+
+/// Offset giving a jump distance of `jump_distance` at `njs`. `None` if it can't be reached, i.e. it's shorter than [MIN_HALF_JUMP_DURATION] allows
+pub fn offset_for_jump_distance(jump_distance: f64, njs: f64, bpm: f64) -> Option<f64> {
+    if njs <= 0.0 || bpm <= 0.0 {
+        return None;
+    }
+    let duration = jump_distance / (2.0 * njs * 60.0 / bpm);
+    if duration < MIN_HALF_JUMP_DURATION {
+        return None;
+    }
+    Some(duration - base_half_jump_duration(njs, bpm))
+}
+
+/// The calculations above for a difficulty. `bpm` is the level's, [crate::BeatmapSetMeta::bpm]
+impl BeatmapMeta {
+    pub fn half_jump_duration(&self, bpm: f64) -> f64 {
+        half_jump_duration(self.note_jump_speed, self.note_jump_start_beat_offset, bpm)
+    }
+
+    pub fn jump_distance(&self, bpm: f64) -> f64 {
+        jump_distance(self.note_jump_speed, self.note_jump_start_beat_offset, bpm)
+    }
+
+    /// In milliseconds
+    pub fn reaction_time(&self, bpm: f64) -> f64 {
+        reaction_time(self.note_jump_speed, self.note_jump_start_beat_offset, bpm)
+    }
+
+    /// Sets the offset so the jump distance becomes `jump_distance`, returning it. Nothing changes if it can't be reached
+    pub fn set_jump_distance(&mut self, jump_distance: f64, bpm: f64) -> Option<f64> {
+        let offset = offset_for_jump_distance(jump_distance, self.note_jump_speed, bpm)?;
+        self.note_jump_start_beat_offset = offset;
+        Some(offset)
+    }
+}
@@ -0,0 +1,328 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Seek, Write};
+use std::path::Path;
+use sha1_smol::Sha1;
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+use crate::error::{ConversionWarning, Error, Result};
+use crate::types::common::Difficulty;
+use crate::types::primary::{AudioData, Event};
+use crate::{Beatmap, BeatmapSetMeta, Timing};
+
+/// A whole level: the contents of `Info.dat` and every difficulty it lists
+#[derive(Debug, Clone, PartialEq)]
+pub struct Level {
+    pub meta: BeatmapSetMeta,
+    /// Keyed by characteristic (game mode) and difficulty
+    pub beatmaps: HashMap<(String, Difficulty), Beatmap>,
+    /// Parsed audio data file (`AudioData.dat`, or `BPMInfo.dat` in older levels), if the level has one. The raw file stays in [Level::assets], but this is what gets packaged.
+    /// An older level's `BPMInfo.dat` that can't be parsed is left out
+    pub audio_data: Option<AudioData>,
+    /// Every other file of the level (song, cover and anything else that was lying around), keyed by filename
+    pub assets: HashMap<String, Vec<u8>>,
+    /// Hash of the files the level was read from, as computed by [Level::hash_bytes]. Edits made since aren't reflected
+    pub source_hash: Option<String>,
+}
+
+impl Level {
+    pub fn open_dir(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let names = fs::read_dir(path)?
+            .filter_map(|x| x.ok())
+            .filter(|x| x.file_type().map(|x| x.is_file()).unwrap_or(false))
+            .filter_map(|x| x.file_name().into_string().ok())
+            .collect();
+        Self::load(names, |name| Ok(fs::read(path.join(name))?))
+    }
+
+    pub fn open_zip(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Reads a level from a zip archive
+    pub fn from_reader(reader: impl Read + Seek) -> Result<Self> {
+        let mut archive = ZipArchive::new(reader)?;
+        let names = archive.file_names().filter(|x| !x.ends_with('/')).map(String::from).collect();
+        Self::load(names, |name| {
+            let mut data = vec![];
+            archive.by_name(name)?.read_to_end(&mut data)?;
+            Ok(data)
+        })
+    }
+
+    fn load(names: Vec<String>, mut read: impl FnMut(&str) -> Result<Vec<u8>>) -> Result<Self> {
+        let info_filename = find_file(&names, "Info.dat")?;
+        let info = read(&info_filename)?;
+        let meta = BeatmapSetMeta::read_from_str(&decode(&info))?;
+
+        let is_v4 = meta.version.starts_with('4');
+        let mut used_names = vec![info_filename];
+        let mut beatmaps = HashMap::new();
+        let mut hashed_files = vec![];
+        for set in &meta.difficulty_sets {
+            for beatmap_meta in &set.beatmaps {
+                let filename = find_file(&names, &beatmap_meta.filename)?;
+                let data = read(&filename)?;
+                let lightshow = match &beatmap_meta.lightshow_filename {
+                    Some(lightshow_filename) => {
+                        let lightshow_filename = find_file(&names, lightshow_filename)?;
+                        let lightshow = read(&lightshow_filename)?;
+                        if !used_names.contains(&lightshow_filename) {
+                            used_names.push(lightshow_filename.clone());
+                        }
+                        Some((lightshow_filename, lightshow))
+                    },
+                    None => None
+                };
+                let beatmap = Beatmap::read_from_str_v4(&decode(&data), lightshow.as_ref().map(|(_, x)| decode(x)).as_deref())
+                    .map_err(|err| Error::DifficultyParsingFailed { filename: beatmap_meta.filename.clone(), source: Box::new(err) })?;
+                beatmaps.insert((set.game_mode.clone(), beatmap_meta.difficulty), beatmap);
+                hashed_files.push((filename.clone(), data));
+                hashed_files.extend(lightshow.filter(|_| is_v4));
+                used_names.push(filename);
+            }
+        }
+
+        let mut assets = HashMap::new();
+        for name in names.iter().filter(|x| !used_names.contains(x)) {
+            assets.insert(name.clone(), read(name)?);
+        }
+        let audio_data_name = find_file(assets.keys(), &audio_data_filename(&meta)).ok();
+        let audio_data = match &audio_data_name {
+            Some(name) if is_v4 => Some(AudioData::read_from_str(&decode(&assets[name]))?),
+            // Only editors read `BPMInfo.dat`, so a broken one stays a plain asset
+            Some(name) => AudioData::read_from_str(&decode(&assets[name])).ok(),
+            None => None
+        };
+        if let Some(name) = audio_data_name.filter(|_| is_v4) {
+            hashed_files.insert(0, (name.clone(), assets[&name].clone()));
+        }
+        let source_hash = Some(hash_files(&info, hashed_files.iter().map(|(name, data)| (name.as_str(), data.as_slice())), is_v4));
+        Ok(Self { meta, beatmaps, audio_data, assets, source_hash })
+    }
+
+    /// The level hash used by BeatSaver, SongCore and leaderboards: uppercase hex SHA-1 of `Info.dat` followed by the files it references.
+    /// For older levels that's every difficulty file in the order `Info.dat` lists them. For v4 levels, it's the audio data file, then every difficulty file and its lightshow file, each file only once
+    pub fn hash_bytes(info: &[u8], files: &[impl AsRef<[u8]>]) -> String {
+        let mut hasher = Sha1::new();
+        hasher.update(info);
+        for file in files {
+            hasher.update(file.as_ref());
+        }
+        hasher.digest().to_string().to_uppercase()
+    }
+
+    /// Hash of the level as it would be packaged by [Level::write_zip]. For a level read from disk, see [Level::source_hash]
+    pub fn hash(&self) -> Result<String> {
+        let LevelFiles { info, audio_data, difficulties, .. } = self.files()?;
+        let is_v4 = self.is_v4();
+        let audio_data_filename = audio_data_filename(&self.meta);
+        let mut files = vec![];
+        if let Some(audio_data) = audio_data.as_ref().filter(|_| is_v4) {
+            files.push((audio_data_filename.as_str(), audio_data.as_bytes()));
+        }
+        for DifficultyFile { filename, data, lightshow } in &difficulties {
+            files.push((filename, data.as_bytes()));
+            files.extend(lightshow.as_ref().map(|(name, data)| (*name, data.as_bytes())));
+        }
+        Ok(hash_files(info.as_bytes(), files, is_v4))
+    }
+
+    /// `Info.dat` and the difficulties in the version the level is in: v4 levels keep their lightshows, older ones are written as v2 info with v2 or v3 difficulties.
+    /// Fails if a v4 difficulty has lighting but no lightshow file to keep it in
+    fn files(&self) -> Result<LevelFiles<'_>> {
+        let is_v4 = self.is_v4();
+        let (info, mut warnings) = if is_v4 { self.meta.write_to_string_v4()? } else { (self.meta.write_to_string()?, vec![]) };
+        let mut beatmap_files = vec![];
+        for set in &self.meta.difficulty_sets {
+            for beatmap_meta in &set.beatmaps {
+                let beatmap = self.beatmaps.get(&(set.game_mode.clone(), beatmap_meta.difficulty))
+                    .ok_or_else(|| Error::MissingFile(beatmap_meta.filename.clone()))?;
+                let filename = beatmap_meta.filename.as_str();
+                if is_v4 && beatmap.version.starts_with('4') {
+                    let (data, lightshow, beatmap_warnings) = beatmap.write_to_strings_v4()?;
+                    warnings.extend(beatmap_warnings);
+                    let lightshow = match beatmap_meta.lightshow_filename.as_deref() {
+                        Some(name) => Some((name, lightshow)),
+                        None if has_lightshow(beatmap) => return Err(Error::MissingLightshow(beatmap_meta.filename.clone())),
+                        None => None,
+                    };
+                    beatmap_files.push(DifficultyFile { filename, data, lightshow });
+                } else if beatmap.version.starts_with('2') {
+                    let (data, beatmap_warnings) = beatmap.write_to_string_v2()?;
+                    warnings.extend(beatmap_warnings);
+                    beatmap_files.push(DifficultyFile { filename, data, lightshow: None });
+                } else {
+                    let (data, beatmap_warnings) = beatmap.write_to_string()?;
+                    warnings.extend(beatmap_warnings);
+                    beatmap_files.push(DifficultyFile { filename, data, lightshow: None });
+                }
+            }
+        }
+        let audio_data = match &self.audio_data {
+            Some(audio_data) if is_v4 => Some(audio_data.write_to_string()?),
+            Some(audio_data) => Some(audio_data.write_to_string_v2()?),
+            None => None,
+        };
+        Ok(LevelFiles { info, audio_data, difficulties: beatmap_files, warnings })
+    }
+
+    /// Packages the level into a zip archive, ready to be uploaded to BeatSaver. Returns what was lost converting the files, see [Level::to_writer]
+    pub fn write_zip(&self, path: impl AsRef<Path>) -> Result<Vec<ConversionWarning>> {
+        self.to_writer(BufWriter::new(File::create(path)?))
+    }
+
+    /// Writes the level as a zip archive, keeping the version it was read in. Only `Info.dat`, the difficulties and lightshows, the song, preview and cover files and the audio data file are included.
+    /// Returns what was lost converting the files, e.g. v3 features of a difficulty that's kept as v2
+    pub fn to_writer(&self, writer: impl Write + Seek) -> Result<Vec<ConversionWarning>> {
+        self.check_files()?;
+        let LevelFiles { info, audio_data, difficulties, warnings } = self.files()?;
+        let mut zip = ZipWriter::new(writer);
+        zip.start_file("Info.dat", FileOptions::default())?;
+        zip.write_all(info.as_bytes())?;
+        let mut lightshow_filenames = vec![];
+        for DifficultyFile { filename, data, lightshow } in difficulties {
+            zip.start_file(filename, FileOptions::default())?;
+            zip.write_all(data.as_bytes())?;
+            if let Some((lightshow_filename, lightshow)) = lightshow {
+                // Difficulties often share a lightshow
+                if !lightshow_filenames.contains(&lightshow_filename) {
+                    zip.start_file(lightshow_filename, FileOptions::default())?;
+                    zip.write_all(lightshow.as_bytes())?;
+                    lightshow_filenames.push(lightshow_filename);
+                }
+            }
+        }
+        for filename in self.referenced_assets() {
+            let name = find_file(self.assets.keys(), filename)?;
+            zip.start_file(filename, FileOptions::default())?;
+            zip.write_all(&self.assets[&name])?;
+        }
+        let audio_data_filename = audio_data_filename(&self.meta);
+        match audio_data {
+            Some(audio_data) => {
+                zip.start_file(audio_data_filename, FileOptions::default())?;
+                zip.write_all(audio_data.as_bytes())?;
+            },
+            // An older level's `BPMInfo.dat` that didn't parse goes as it is
+            None => if let Ok(name) = find_file(self.assets.keys(), &audio_data_filename) {
+                zip.start_file(name.as_str(), FileOptions::default())?;
+                zip.write_all(&self.assets[&name])?;
+            },
+        }
+        zip.finish()?;
+        Ok(warnings)
+    }
+
+    fn is_v4(&self) -> bool {
+        self.meta.version.starts_with('4')
+    }
+
+    /// Song and cover, and the song preview of a v4 level when it's a separate file
+    fn referenced_assets(&self) -> Vec<&str> {
+        let mut filenames = vec![self.meta.song_filename.as_str(), self.meta.cover_image_filename.as_str()];
+        match &self.meta.song_preview_filename {
+            Some(preview) if self.is_v4() && *preview != self.meta.song_filename => filenames.push(preview),
+            _ => {},
+        }
+        filenames
+    }
+
+    /// Makes sure every file referenced by `Info.dat` is present
+    pub fn check_files(&self) -> Result<()> {
+        for set in &self.meta.difficulty_sets {
+            for beatmap_meta in &set.beatmaps {
+                if !self.beatmaps.contains_key(&(set.game_mode.clone(), beatmap_meta.difficulty)) {
+                    return Err(Error::MissingFile(beatmap_meta.filename.clone()));
+                }
+            }
+        }
+        for filename in self.referenced_assets() {
+            find_file(self.assets.keys(), filename)?;
+        }
+        // v4 needs the audio data file, which is written from [Level::audio_data]
+        if self.is_v4() && (self.meta.audio_data_filename.is_none() || self.audio_data.is_none()) {
+            return Err(Error::MissingFile(audio_data_filename(&self.meta)));
+        }
+        Ok(())
+    }
+
+    /// Assets that aren't referenced by `Info.dat` and won't be packaged
+    pub fn stray_files(&self) -> Vec<&str> {
+        let audio_data_filename = audio_data_filename(&self.meta);
+        let mut referenced = self.referenced_assets();
+        referenced.push(&audio_data_filename);
+        self.assets.keys()
+            .filter(|x| !referenced.iter().any(|name| x.eq_ignore_ascii_case(name)))
+            .map(String::as_str)
+            .collect()
+    }
+
+    pub fn beatmap(&self, game_mode: &str, difficulty: Difficulty) -> Option<&Beatmap> {
+        self.beatmaps.get(&(game_mode.to_string(), difficulty))
+    }
+
+    /// Timing of a difficulty, taken from the audio data file when it's a v4 level that has one
+    pub fn timing(&self, game_mode: &str, difficulty: Difficulty) -> Option<Timing> {
+        let beatmap = self.beatmap(game_mode, difficulty)?;
+        Some(Timing::from_level_data(&self.meta, beatmap, self.audio_data.as_ref()))
+    }
+
+    pub fn beatmap_mut(&mut self, game_mode: &str, difficulty: Difficulty) -> Option<&mut Beatmap> {
+        self.beatmaps.get_mut(&(game_mode.to_string(), difficulty))
+    }
+}
+
+/// Everything [Level::to_writer] writes but the assets, and what was lost converting it
+struct LevelFiles<'a> {
+    info: String,
+    /// Written from [Level::audio_data], in the version of the level
+    audio_data: Option<String>,
+    difficulties: Vec<DifficultyFile<'a>>,
+    warnings: Vec<ConversionWarning>,
+}
+
+/// A difficulty as written by [Level::to_writer], with its lightshow if it's a v4 one
+struct DifficultyFile<'a> {
+    filename: &'a str,
+    data: String,
+    lightshow: Option<(&'a str, String)>,
+}
+
+/// Hashes the named files after `Info.dat`, see [Level::hash_bytes]. v4 levels hash a file only the first time it comes up, since difficulties often share a lightshow
+fn hash_files<'a>(info: &[u8], files: impl IntoIterator<Item = (&'a str, &'a [u8])>, is_v4: bool) -> String {
+    let mut names = vec![];
+    let files: Vec<&[u8]> = files.into_iter()
+        .filter(|(name, _)| {
+            let is_new = !names.contains(name);
+            names.push(*name);
+            is_new || !is_v4
+        })
+        .map(|(_, data)| data)
+        .collect();
+    Level::hash_bytes(info, &files)
+}
+
+/// Whether the beatmap has anything that goes in a v4 lightshow file
+fn has_lightshow(beatmap: &Beatmap) -> bool {
+    !beatmap.waypoints.is_empty() || beatmap.events.iter().any(|x| matches!(x, Event::BasicEvent(_) | Event::ColorBoost(_) | Event::LightEventBox(_)))
+}
+
+/// v4 levels name the file in `Info.dat`, older ones may ship a `BPMInfo.dat`
+fn audio_data_filename(meta: &BeatmapSetMeta) -> String {
+    meta.audio_data_filename.clone().unwrap_or_else(|| "BPMInfo.dat".to_string())
+}
+
+/// Prefers an exact match, but falls back to ignoring case, since `Info.dat` and `info.dat` are both common
+fn find_file<'a>(names: impl IntoIterator<Item = &'a String> + Clone, name: &str) -> Result<String> {
+    names.clone().into_iter()
+        .find(|x| *x == name)
+        .or_else(|| names.into_iter().find(|x| x.eq_ignore_ascii_case(name)))
+        .cloned()
+        .ok_or_else(|| Error::MissingFile(name.to_string()))
+}
+
+fn decode(data: &[u8]) -> String {
+    String::from_utf8_lossy(data).trim_start_matches('\u{feff}').to_string()
+}
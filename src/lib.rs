@@ -1,20 +1,45 @@
-//! See [Beatmap] and [BeatmapSetMeta]
+//! See [Beatmap] and [BeatmapSetMeta], or [Level] to load both at once
 
 pub mod error;
 pub mod types;
+pub mod timing;
+pub mod level;
+pub mod animation;
+pub mod lighting;
+pub mod environment;
+pub mod stats;
+pub mod parity;
+pub mod jump;
+pub mod vision;
 
 pub use types::primary::BeatmapSetMeta;
 pub use types::primary::Beatmap;
-
-// TODO: writing
-// TODO: implement timing calculations
+pub use timing::Timing;
+pub use level::Level;
 
 #[cfg(test)]
 mod tests {
-    use std::fs::File;
-    use crate::{Beatmap, BeatmapSetMeta};
-    use crate::error::Result;
-    use std::io::{BufReader, Read};
+    use crate::{Beatmap, BeatmapSetMeta, Level, Timing};
+    use crate::types::primary::{AudioData, BasicEvent, Bomb, BPMEvent, ColorBoost, ColorScheme, DifficultySet, Event, Note, Obstacle, Rotation, Slider};
+    use crate::error::{ConversionWarning, EnvironmentWarning, Error, ParityWarning, Result};
+    use crate::types::common::{Difficulty, Direction, NoteColor, SliderMidAnchorMode};
+    use crate::types::custom_data::{ChromaData, NoodleData};
+    use crate::types::heck::{CustomEventKind, HeckEasing, Modifier, Operation, Point, PointDefinition, PointValue};
+    use crate::animation::{sample, sample_with_bases, PointKind};
+    use crate::lighting::boxes::{evaluate, evaluate_in_environment};
+    use crate::environment::{Environment, Environments, LightGroup};
+    use crate::stats::Stats;
+    use crate::parity::{analyze, Parity};
+    use crate::jump::{half_jump_duration, jump_distance, offset_for_jump_distance, reaction_time};
+    use crate::vision::{vision_blocks, Blocker};
+    use crate::lighting::basic::{LightState, LightingState, FADE_DURATION};
+    use crate::types::common::{BasicEventType, BoxFilterOrdering, DistributionKind, Easing, LightColor, LightValue, LimitKind, TransitionKind};
+    use crate::types::lightning::{BoxFilter, BoxFilterSettings, LightColorEvent, LightEventBox, LightEventLane, LightEvents};
+    use crate::types::schema::{BeatmapFile, Info, InfoFile};
+    use crate::types::songcore::{Contributor, SongCoreDifficultyData, SongCoreLevelData};
+    use std::collections::HashMap;
+    use serde_json::json;
+    use std::io::{Cursor, Write};
 
     #[test]
     fn reading_level() -> Result<()> {
@@ -22,6 +47,13 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn writing_level() -> Result<()> {
+        let level = BeatmapSetMeta::read_from_file("test_beatmap/info.dat")?;
+        assert_eq!(level, BeatmapSetMeta::read_from_str(&level.write_to_string()?)?);
+        Ok(())
+    }
+
     #[test]
     fn reading_beatmap_v2() -> Result<()> {
         let _beatmap = Beatmap::read_from_file("test_beatmap/beatmapv2.dat")?;
@@ -34,26 +66,724 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn writing_beatmap_v3() -> Result<()> {
+        let beatmap = Beatmap::read_from_file("test_beatmap/beatmapv3.dat")?;
+        let (data, warnings) = beatmap.write_to_string()?;
+        assert!(warnings.is_empty());
+        assert_eq!(beatmap, Beatmap::read_from_str(&data)?);
+        Ok(())
+    }
+
+    #[test]
+    fn reading_special_events_v2() -> Result<()> {
+        let beatmap = Beatmap::read_from_str(r#"{"_version": "2.0.0", "_notes": [], "_obstacles": [], "_events": [
+            {"_time": 1, "_type": 5, "_value": 1},
+            {"_time": 2, "_type": 14, "_value": 2},
+            {"_time": 3, "_type": 15, "_value": 1405},
+            {"_time": 4, "_type": 100, "_value": 0, "_floatValue": 140}
+        ]}"#)?;
+        assert_eq!(beatmap.events, vec![
+            Event::ColorBoost(Box::new(ColorBoost { beat: 1.0, enable: true, extras: HashMap::new() })),
+            Event::Rotation(Box::new(Rotation { beat: 2.0, is_late: false, value: -30.0, extras: HashMap::new() })),
+            Event::Rotation(Box::new(Rotation { beat: 3.0, is_late: true, value: 45.0, extras: HashMap::new() })),
+            Event::BPM(Box::new(BPMEvent { beat: 4.0, value: 140.0, extras: HashMap::new() })),
+        ]);
+        Ok(())
+    }
+
+    #[test]
+    fn reading_custom_data() -> Result<()> {
+        let beatmap = Beatmap::read_from_str(r#"{"version": "3.2.0", "bpmEvents": [], "rotationEvents": [], "colorNotes": [
+            {"b": 1, "x": 0, "y": 0, "c": 0, "d": 1, "a": 0, "customData": {"coordinates": [-1, 2.5], "track": "intro", "color": [1, 0, 0], "uninteractable": true}}
+        ], "bombNotes": [], "obstacles": [], "sliders": [], "burstSliders": [], "waypoints": [], "basicBeatmapEvents": [],
+        "colorBoostBeatmapEvents": [], "lightColorEventBoxGroups": [], "lightRotationEventBoxGroups": [], "useNormalEventsAsCompatibleEvents": false}"#)?;
+        let Event::Note(note) = &beatmap.events[0] else { panic!() };
+        assert_eq!(note.coordinates(), Some([-1.0, 2.5]));
+        assert_eq!(note.track(), Some(vec!["intro".to_string()]));
+        assert_eq!(note.color(), Some([1.0, 0.0, 0.0, 1.0]));
+        assert_eq!(note.uninteractable(), Some(true));
+
+        let (data, _) = beatmap.write_to_string_v2()?;
+        let Event::Note(mut note) = Beatmap::read_from_str(&data)?.events.remove(0) else { panic!() };
+        assert_eq!(note.coordinates(), Some([-1.0, 2.5]));
+        note.set_track(None);
+        assert_eq!(note.track(), None);
+        note.set_coordinates(Some([0.0, 1.0]));
+        note.set_uninteractable(Some(true));
+        assert_eq!(note.custom_data["_position"], json!([0.0, 1.0]));
+        assert_eq!(note.custom_data["_interactable"], json!(false));
+        assert_eq!(note.uninteractable(), Some(true));
+        assert!(!note.custom_data.contains_key("coordinates"));
+
+        let v2 = Beatmap::read_from_str(r#"{"_version": "2.6.0", "_notes": [
+            {"_time": 1, "_lineIndex": 0, "_lineLayer": 0, "_type": 0, "_cutDirection": 1, "_customData": {"_position": [1, 2], "_interactable": false, "_animation": {"_dissolve": "fade"}, "_foo": 1}}
+        ], "_obstacles": [], "_events": [{"_time": 2, "_type": 4, "_value": 1, "_customData": {"_rotation": 45}}]}"#)?;
+        let (data, warnings) = v2.write_to_string()?;
+        assert_eq!(warnings, vec![ConversionWarning::CustomDataKept { beat: 1.0, key: "_foo".to_string() }]);
+        let v3 = Beatmap::read_from_str(&data)?;
+        let Some(Event::Note(note)) = v3.events.iter().find(|x| matches!(x, Event::Note(_))) else { panic!() };
+        assert_eq!((note.coordinates(), note.uninteractable()), (Some([1.0, 2.0]), Some(true)));
+        assert_eq!(note.custom_data.get("animation"), Some(&json!({"dissolve": "fade"})));
+        assert_eq!(note.custom_data.keys().filter(|x| x.starts_with('_')).collect::<Vec<_>>(), vec!["_foo"]);
+        let Some(Event::BasicEvent(event)) = v3.events.iter().find(|x| matches!(x, Event::BasicEvent(_))) else { panic!() };
+        assert_eq!(event.custom_data.get("rotation"), Some(&json!(45)));
+        let (file, _, warnings) = v2.to_v4_files();
+        assert_eq!(file.colorNotesData[0].customData.get("coordinates"), Some(&json!([1, 2])));
+        assert_eq!(warnings.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn reading_heck_events() -> Result<()> {
+        let mut beatmap = Beatmap::read_from_str(r#"{"version": "3.2.0", "bpmEvents": [], "rotationEvents": [], "colorNotes": [],
+        "bombNotes": [], "obstacles": [], "sliders": [], "burstSliders": [], "waypoints": [], "basicBeatmapEvents": [],
+        "colorBoostBeatmapEvents": [], "lightColorEventBoxGroups": [], "lightRotationEventBoxGroups": [], "useNormalEventsAsCompatibleEvents": false,
+        "customData": {
+            "customEvents": [
+                {"b": 2, "t": "AnimateTrack", "d": {"track": "intro", "duration": 4, "easing": "easeOutQuad", "position": "slide", "dissolve": [0]}},
+                {"b": 3, "t": "AssignTrackParent", "d": {"childrenTracks": ["a", "b"], "parentTrack": "intro"}}
+            ],
+            "pointDefinitions": {"slide": [[0, 0, 0, 0], [0, 5, 0, 1, "easeInOutSine", "splineCatmullRom"], ["baseHeadLocalPosition", [0, 1, 0, "opAdd"], 1]]}
+        }}"#)?;
+        let events = beatmap.custom_events()?;
+        let CustomEventKind::AnimateTrack(animation) = &events[0].kind else { panic!() };
+        assert_eq!(animation.track, vec!["intro".to_string()]);
+        assert_eq!(animation.easing, Some(HeckEasing::OutQuad));
+        assert_eq!(animation.properties["position"], PointDefinition::Named("slide".to_string()));
+        assert_eq!(animation.properties["dissolve"], PointDefinition::Points(vec![Point::new(vec![0.0], 0.0)]));
+        assert!(matches!(&events[1].kind, CustomEventKind::AssignTrackParent(event) if event.children_tracks.len() == 2));
+
+        let definitions = beatmap.point_definitions()?;
+        let points = animation.properties["position"].resolve(&definitions).unwrap();
+        assert_eq!(points[1].easing, Some(HeckEasing::InOutSine));
+        assert!(points[1].spline);
+        assert_eq!(points[2].values, vec![PointValue::Base("baseHeadLocalPosition".to_string())]);
+        assert_eq!(points[2].modifiers[0].operation, Operation::Add);
+
+        beatmap.set_custom_events(&events);
+        beatmap.set_point_definitions(&definitions);
+        assert_eq!(beatmap.custom_events()?, events);
+        assert_eq!(beatmap.point_definitions()?, definitions);
+
+        let offset = json!([{"b": 1, "t": "AnimateTrack", "d": {"track": "a", "duration": 1, "offsetPosition": [[0, 0, 0, 0], [0, 1, 0, 1]], "localPosition": [0, 1, 0]}}]);
+        let beatmap = Beatmap { custom_data: HashMap::from([("customEvents".to_string(), offset)]), ..beatmap };
+        let (data, warnings) = beatmap.write_to_string_v2()?;
+        assert_eq!(warnings, vec![ConversionWarning::CustomEventPropertyKept { beat: 1.0, property: "localPosition".to_string() }]);
+        let v2: serde_json::Value = serde_json::from_str(&data).unwrap();
+        assert!(v2["_customData"]["_customEvents"][0]["_data"].get("_position").is_some());
+        let mut read = Beatmap::read_from_str(&data)?;
+        assert_eq!(read.custom_events()?, beatmap.custom_events()?);
+
+        read.custom_data.insert("_environment".to_string(), json!([{"_id": "Laser", "_lookupMethod": "Contains", "_active": false}]));
+        read.set_point_definitions(&HashMap::from([("fade".to_string(), vec![Point::new(vec![1.0], 0.0)])]));
+        let (data, warnings) = read.write_to_string()?;
+        assert!(warnings.contains(&ConversionWarning::BeatmapCustomDataKept { key: "_environment".to_string() }));
+        let v3 = Beatmap::read_from_str(&data)?;
+        assert_eq!(v3.custom_events()?, beatmap.custom_events()?);
+        assert_eq!(v3.point_definitions()?, read.point_definitions()?);
+        assert!(!v3.custom_data.contains_key("_customEvents"));
+        Ok(())
+    }
+
+    #[test]
+    fn sampling_points() {
+        let mut eased = Point::new(vec![10.0], 1.0);
+        eased.easing = Some(HeckEasing::InQuad);
+        let points = [Point::new(vec![0.0], 0.0), eased];
+        assert_eq!(sample(&points, -1.0, PointKind::Vec1), Some(vec![0.0]));
+        assert_eq!(sample(&points, 0.5, PointKind::Vec1), Some(vec![2.5]));
+        assert_eq!(sample(&points, 2.0, PointKind::Vec1), Some(vec![10.0]));
+
+        let mut spline = Point::new(vec![1.0, 1.0, 0.0], 0.5);
+        spline.spline = true;
+        let points = [Point::new(vec![0.0, 0.0, 0.0], 0.0), spline, Point::new(vec![2.0, 0.0, 0.0], 1.0)];
+        assert_eq!(sample(&points, 0.5, PointKind::Vec3), Some(vec![1.0, 1.0, 0.0]));
+        assert!(sample(&points, 0.25, PointKind::Vec3).unwrap()[1] > 0.5);
+        let mut short = Point::new(vec![1.0, 1.0], 0.5);
+        short.spline = true;
+        assert_eq!(sample(&[Point::new(vec![0.0, 0.0, 0.0], 0.0), short], 0.25, PointKind::Vec3), None);
+
+        let mut hsv = Point::new(vec![1.0, 0.0], 1.0);
+        hsv.hsv_lerp = true;
+        assert_eq!(sample(&[Point::new(vec![0.0, 0.0, 1.0, 1.0], 0.0), hsv], 0.5, PointKind::Vec4), None);
+
+        let points = [Point::new(vec![0.0, 0.0, 0.0], 0.0), Point::new(vec![0.0, 90.0, 0.0], 1.0)];
+        let rotation = sample(&points, 0.5, PointKind::Quaternion).unwrap();
+        let expected = [0.0, 22.5f64.to_radians().sin(), 0.0, 22.5f64.to_radians().cos()];
+        assert!(rotation.iter().zip(expected).all(|(a, b)| (a - b).abs() < 1e-9));
+
+        let mut based = Point::new(vec![], 0.0);
+        based.values.push(PointValue::Base("baseHeadLocalPosition".to_string()));
+        based.modifiers.push(Modifier { values: vec![PointValue::Number(2.0)], modifiers: vec![], operation: Operation::Mul });
+        assert_eq!(sample(&[based.clone()], 0.0, PointKind::Vec3), None);
+        assert_eq!(sample_with_bases(&[based], 0.0, PointKind::Vec3, |_| Some(vec![1.0, 2.0, 3.0])), Some(vec![2.0, 4.0, 6.0]));
+    }
+
+    #[test]
+    fn writing_beatmap_v2() -> Result<()> {
+        let beatmap = Beatmap::read_from_file("test_beatmap/beatmapv2.dat")?;
+        let (data, warnings) = beatmap.write_to_string_v2()?;
+        assert!(warnings.is_empty());
+        assert_eq!(beatmap, Beatmap::read_from_str(&data)?);
+
+        let (_, warnings) = Beatmap::read_from_file("test_beatmap/beatmapv3.dat")?.write_to_string_v2()?;
+        assert!(warnings.iter().any(|x| matches!(x, ConversionWarning::AngleOffsetDropped { .. })));
+
+        let custom_data = HashMap::from([("coordinates".to_string(), json!([1, 2])), ("uninteractable".to_string(), json!(true)), ("foo".to_string(), json!(1))]);
+        let events = vec![
+            Event::Note(Box::new(Note { beat: 1.0, x: 0, y: 0, color: NoteColor::Red, direction: Direction::Down, angle_offset: 0.0, custom_data, extras: HashMap::new() })),
+            Event::Rotation(Box::new(Rotation { beat: 2.0, is_late: false, value: 20.0, extras: HashMap::new() })),
+            Event::Rotation(Box::new(Rotation { beat: 3.0, is_late: false, value: 22.5, extras: HashMap::new() })),
+        ];
+        let mut beatmap = Beatmap { events, ..Beatmap::read_from_file("test_beatmap/beatmapv3.dat")? };
+        beatmap.set_point_definitions(&HashMap::from([("path".to_string(), vec![Point { values: vec![PointValue::Number(0.0)], time: 0.0, easing: None, spline: false, hsv_lerp: false, modifiers: vec![] }])]));
+        let (data, warnings) = beatmap.write_to_string_v2()?;
+        assert!(warnings.contains(&ConversionWarning::CustomDataKept { beat: 1.0, key: "foo".to_string() }));
+        assert!(warnings.contains(&ConversionWarning::RotationRounded { beat: 3.0, value: 22.5, rounded_to: 23.0 }));
+        assert!(warnings.contains(&ConversionWarning::RotationNeedsMappingExtensions { beat: 2.0, value: 20.0 }));
+        let read = Beatmap::read_from_str(&data)?;
+        let Some(Event::Note(note)) = read.events.iter().find(|x| matches!(x, Event::Note(_))) else { panic!() };
+        assert_eq!((note.custom_data.get("_position"), note.custom_data.get("_interactable")), (Some(&json!([1, 2])), Some(&json!(false))));
+        let rotations: Vec<f64> = read.events.iter().filter_map(|x| if let Event::Rotation(x) = x { Some(x.value) } else { None }).collect();
+        assert_eq!(rotations, vec![20.0, 23.0]);
+        assert_eq!(read.point_definitions()?, beatmap.point_definitions()?);
+        Ok(())
+    }
+
+    #[test]
+    fn writing_beatmap_v4() -> Result<()> {
+        let mut beatmap = Beatmap::read_from_file("test_beatmap/beatmapv3.dat")?;
+        beatmap.events.push(Event::BPM(Box::new(BPMEvent { beat: 8.0, value: 120.0, extras: HashMap::new() })));
+        let filter = BoxFilter {
+            chunks: 0,
+            settings: BoxFilterSettings::StepAndOffset { start: 0, skip: 1 },
+            reverse: false,
+            ordering: BoxFilterOrdering::Standard1,
+            random_seed: 0,
+            limit: 0.0,
+            limit_kind: LimitKind::Sections,
+            extras: HashMap::new(),
+        };
+        let event = LightColorEvent { relative_beat: 0.0, transition_kind: TransitionKind::Transition, color: LightColor::Red, brightness: 1.0, frequency: 0, extras: HashMap::new() };
+        let lane = LightEventLane {
+            filter,
+            beat_dist: 1.0,
+            beat_dist_kind: DistributionKind::Wave,
+            dist: 0.0,
+            dist_kind: DistributionKind::Wave,
+            dist_affects_first_event: false,
+            dist_easing: Some(Easing::Linear),
+            axis: None,
+            reverse: None,
+            events: LightEvents::Color(vec![event.clone(), LightColorEvent { relative_beat: 2.0, ..event }]),
+            extras: HashMap::new(),
+        };
+        beatmap.events.push(Event::LightEventBox(Box::new(LightEventBox { beat: 4.0, group: 0, lanes: vec![lane.clone(), lane], extras: HashMap::new() })));
+        let (file, lightshow, warnings) = beatmap.to_v4_files();
+        assert_eq!(warnings, vec![ConversionWarning::BpmEventDropped { beat: 8.0 }]);
+        assert!(file.colorNotesData.len() < file.colorNotes.len());
+        assert!(lightshow.basicEventsData.len() < lightshow.basicEvents.len());
+        assert_eq!((lightshow.indexFilters.len(), lightshow.lightColorEvents.len()), (1, 1));
+
+        let (data, lightshow_data, _) = beatmap.write_to_strings_v4()?;
+        assert!(matches!(BeatmapFile::read_from_str(&data)?, BeatmapFile::V4(_)));
+        let read = Beatmap::read_from_str_v4(&data, Some(&lightshow_data))?;
+        let expected: Vec<&Event> = beatmap.events.iter().filter(|x| !matches!(x, Event::BPM(_))).collect();
+        assert_eq!(read.events.len(), expected.len());
+        assert!(expected.iter().all(|x| read.events.contains(x)));
+        assert!(Beatmap::read_from_str(&data)?.events.iter().all(|x| !matches!(x, Event::BasicEvent(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip_schema() -> Result<()> {
+        for path in ["test_beatmap/beatmapv2.dat", "test_beatmap/beatmapv3.dat"] {
+            let file = BeatmapFile::read_from_file(path)?;
+            assert_eq!(file, BeatmapFile::read_from_str(&file.write_to_string()?)?);
+        }
+        let info = Info::read_from_file("test_beatmap/info.dat")?;
+        assert_eq!(info, Info::read_from_str(&info.write_to_string()?)?);
+
+        let BeatmapFile::Old(file) = BeatmapFile::read_from_file("test_beatmap/beatmapv2.dat")? else { panic!() };
+        assert!(file.extras.contains_key("_bookmarks"));
+
+        // Unknown keys survive going through the primary types too
+        let as_value = |data: &str| serde_json::from_str::<serde_json::Value>(data).unwrap();
+        let mut v3 = as_value(&std::fs::read_to_string("test_beatmap/beatmapv3.dat")?);
+        v3["editorData"] = json!({ "bookmarks": [] });
+        v3["colorNotes"][0]["editorMark"] = json!(1);
+        let v3 = v3.to_string();
+        let expected = as_value(&BeatmapFile::read_from_str(&v3)?.write_to_string()?);
+        assert_eq!(as_value(&Beatmap::read_from_str(&v3)?.write_to_string()?.0), expected);
+        assert_eq!(expected["colorNotes"][0]["editorMark"], json!(1));
+
+        let v2 = std::fs::read_to_string("test_beatmap/beatmapv2.dat")?;
+        let expected = as_value(&BeatmapFile::read_from_str(&v2)?.write_to_string()?);
+        let written = as_value(&Beatmap::read_from_str(&v2)?.write_to_string_v2()?.0);
+        assert_eq!((&written["_bookmarks"], &written["_BPMChanges"]), (&expected["_bookmarks"], &expected["_BPMChanges"]));
+
+        let mut info = as_value(&std::fs::read_to_string("test_beatmap/info.dat")?);
+        info["_editorData"] = json!(true);
+        info["_difficultyBeatmapSets"][0]["_difficultyBeatmaps"][0]["_editorData"] = json!(true);
+        let info = info.to_string();
+        let expected = as_value(&Info::read_from_str(&info)?.write_to_string()?);
+        assert_eq!(as_value(&BeatmapSetMeta::read_from_str(&info)?.write_to_string()?), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn timing() -> Result<()> {
+        let level = BeatmapSetMeta::read_from_file("test_beatmap/info.dat")?;
+        let beatmap = Beatmap::read_from_file("test_beatmap/beatmapv3.dat")?;
+        let timing = Timing::from_beatmap(&level, &beatmap);
+        assert_eq!(timing.beat_to_seconds(105.0), 60.0);
+
+        let events = [BPMEvent { beat: 4.0, value: 60.0, extras: HashMap::new() }, BPMEvent { beat: 8.0, value: 240.0, extras: HashMap::new() }];
+        let timing = Timing::new(120.0, 0.5, &events);
+        assert_eq!(timing.beat_to_seconds(2.0), 1.5);
+        assert_eq!(timing.beat_to_seconds(6.0), 4.5);
+        assert_eq!(timing.beat_to_seconds(12.0), 7.5);
+        assert_eq!(timing.seconds_to_beat(4.5), 6.0);
+        assert_eq!(timing.seconds_to_beat(7.5), 12.0);
+        Ok(())
+    }
+
+    #[test]
+    fn timing_from_audio_data() -> Result<()> {
+        let data = r#"{"version":"4.0.0","songChecksum":"","songSampleCount":441000,"songFrequency":44100,
+            "bpmData":[{"si":0,"ei":88200,"sb":0,"eb":4},{"si":88200,"ei":132300,"sb":4,"eb":5}],
+            "lufsData":[{"si":0,"ei":441000,"l":-8.5}]}"#;
+        let audio_data = AudioData::read_from_str(data)?;
+        assert_eq!(audio_data.lufs_regions[0].lufs, -8.5);
+        assert_eq!(AudioData::read_from_str(&audio_data.write_to_string()?)?, audio_data);
+        let old = AudioData::read_from_str(&audio_data.write_to_string_v2()?)?;
+        assert_eq!((old.version.as_str(), &old.bpm_regions), ("2.0.0", &audio_data.bpm_regions));
+
+        let mut meta = BeatmapSetMeta::read_from_file("test_beatmap/info.dat")?;
+        let beatmap = Beatmap::read_from_file("test_beatmap/beatmapv3.dat")?;
+        assert_eq!(Timing::from_level_data(&meta, &beatmap, Some(&audio_data)), Timing::from_beatmap(&meta, &beatmap));
+        meta.version = "4.0.1".to_string();
+        let timing = Timing::from_level_data(&meta, &beatmap, Some(&audio_data));
+        assert_eq!(timing.beat_to_seconds(4.0), 2.0);
+        assert_eq!(timing.beat_to_seconds(5.0), 3.0);
+        assert_eq!(timing.seconds_to_beat(2.5), 4.5);
+        assert_eq!(Timing::from_level_data(&meta, &beatmap, None), Timing::from_beatmap(&meta, &beatmap));
+        Ok(())
+    }
+
+    #[test]
+    fn evaluating_light_boxes() {
+        let filter = BoxFilter {
+            chunks: 0,
+            settings: BoxFilterSettings::Sections { count: 2, index: 1 },
+            reverse: false,
+            ordering: BoxFilterOrdering::Standard1,
+            random_seed: 0,
+            limit: 0.0,
+            limit_kind: LimitKind::Sections,
+            extras: HashMap::new(),
+        };
+        assert_eq!(filter.apply(4), (vec![vec![2], vec![3]], 2));
+        let stepped = BoxFilter { settings: BoxFilterSettings::StepAndOffset { start: 1, skip: 2 }, reverse: true, ..filter.clone() };
+        assert_eq!(stepped.apply(6).0, vec![vec![4], vec![2], vec![0]]);
+        let random = BoxFilter { settings: BoxFilterSettings::StepAndOffset { start: 0, skip: 1 }, ordering: BoxFilterOrdering::Random, random_seed: 7, ..filter.clone() };
+        let mut shuffled = random.apply(8).0;
+        assert_eq!(shuffled, random.apply(8).0);
+        shuffled.sort();
+        assert_eq!(shuffled, (0..8).map(|x| vec![x]).collect::<Vec<_>>());
+
+        let event_box = LightEventBox {
+            beat: 4.0,
+            group: 1,
+            lanes: vec![LightEventLane {
+                filter: BoxFilter { settings: BoxFilterSettings::StepAndOffset { start: 0, skip: 1 }, ..filter },
+                beat_dist: 1.0,
+                beat_dist_kind: DistributionKind::Wave,
+                dist: 0.5,
+                dist_kind: DistributionKind::Step,
+                dist_affects_first_event: true,
+                dist_easing: None,
+                axis: None,
+                reverse: None,
+                events: LightEvents::Color(vec![LightColorEvent {
+                    relative_beat: 0.0,
+                    transition_kind: TransitionKind::Instant,
+                    color: LightColor::Blue,
+                    brightness: 1.0,
+                    frequency: 0,
+                    extras: HashMap::new(),
+                }]),
+                extras: HashMap::new(),
+            }],
+            extras: HashMap::new(),
+        };
+        let scheme = ColorScheme::default();
+        let timelines = evaluate([&event_box], 1, 4, &scheme);
+        assert_eq!(timelines[3].color[0].beat, 4.75);
+        assert_eq!(timelines[2].color[0].brightness, 2.0);
+        assert_eq!(timelines[2].color[0].rgba, [scheme.env_right[0] * 2.0, scheme.env_right[1] * 2.0, scheme.env_right[2] * 2.0, 1.0]);
+        assert!(evaluate([&event_box], 0, 4, &scheme).iter().all(|x| x.color.is_empty()));
+    }
+
+    #[test]
+    fn simulating_basic_lighting() -> Result<()> {
+        let mut beatmap = Beatmap::read_from_file("test_beatmap/beatmapv3.dat")?;
+        let event = |beat: f64, kind: i32, value: i32| Event::BasicEvent(Box::new(BasicEvent { beat, kind, value, float_value: None, custom_data: HashMap::new(), extras: HashMap::new() }));
+        beatmap.events = vec![
+            event(2.0, 0, LightValue::On(LightColor::Blue).into()),
+            event(4.0, 0, LightValue::Transition(LightColor::Red).into()),
+            event(6.0, 0, LightValue::Fade(LightColor::Blue).into()),
+            event(20.0, 0, LightValue::On(LightColor::Red).into()),
+            event(3.0, 12, 4),
+            event(1.0, 9, 0),
+            Event::ColorBoost(Box::new(ColorBoost { beat: 5.0, enable: true, extras: HashMap::new() })),
+        ];
+        let scheme = ColorScheme::default();
+        let lighting = LightingState::new(&beatmap, &Timing::new(60.0, 0.0, []), &scheme);
+        assert_eq!(lighting.light_at(BasicEventType::BackLasers, 1.0), None);
+        let state = lighting.light_at(BasicEventType::BackLasers, 3.0).unwrap();
+        assert_eq!((state.color, state.brightness, state.boost), (LightColor::Blue, 1.0, false));
+        assert_eq!(state.blend_to, Some((LightColor::Red, 0.5)));
+        assert!(lighting.light_at(BasicEventType::BackLasers, 5.0).unwrap().boost);
+        assert_eq!(lighting.rgba_at(BasicEventType::BackLasers, 20.5), Some(scheme.env_left_boost));
+        assert!(lighting.light_at(BasicEventType::BackLasers, 6.5).unwrap().brightness > 0.0);
+        assert!(lighting.light_at(BasicEventType::BackLasers, 10.0).unwrap().is_off());
+        assert_eq!(lighting.laser_speed_at(BasicEventType::LeftLaserSpeed, 42.5), 4.0);
+        assert!(lighting.rings_zoomed(1.0));
+        assert_eq!(BasicEventType::try_from(42), Ok(BasicEventType::SpecialEvent2));
+        assert_eq!(BasicEventType::try_from(1234), Err(1234));
+        assert_eq!(LightValue::try_from(10), Ok(LightValue::Flash(LightColor::White)));
+        assert_eq!((0..=12).map(|x| i32::from(LightValue::try_from(x).unwrap())).collect::<Vec<_>>(), (0..=12).collect::<Vec<_>>());
+        assert_eq!(lighting.dark_stretches(BasicEventType::BackLasers, 5.0), vec![(6.0 + FADE_DURATION, 20.0)]);
+        Ok(())
+    }
+
+    #[test]
+    fn checking_environment() -> Result<()> {
+        let meta = BeatmapSetMeta::read_from_file("test_beatmap/info.dat")?;
+        let mut beatmap = Beatmap::read_from_file("test_beatmap/beatmapv3.dat")?;
+        let mut environments = Environments::builtin();
+        assert_eq!(environments.get(&meta.environment_name).unwrap().check(&beatmap), vec![]);
+
+        assert!(environments.get("WeaveEnvironment").is_none());
+        environments.insert(Environment {
+            name: "TestEnvironment".to_string(),
+            light_groups: (0..4).map(|id| LightGroup { id, light_count: 8 }).collect(),
+            event_types: vec![BasicEventType::ColorBoost],
+        });
+        let environment = environments.get("TestEnvironment").unwrap();
+        assert!(environment.check(&beatmap).iter().all(|x| matches!(x, EnvironmentWarning::UnsupportedEventType { .. })));
+        beatmap.events = vec![Event::LightEventBox(Box::new(LightEventBox { beat: 1.0, group: 20, lanes: vec![], extras: HashMap::new() }))];
+        assert_eq!(environment.check(&beatmap).len(), 1);
+        assert!(evaluate_in_environment(&beatmap, environment, 20, &ColorScheme::default()).is_none());
+        assert_eq!(evaluate_in_environment(&beatmap, environment, 3, &ColorScheme::default()).unwrap().len(), 8);
+        Ok(())
+    }
+
+    fn test_level_zip(expert_plus: &str) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(vec![]));
+        let files = [
+            ("info.dat", "test_beatmap/info.dat"),
+            ("HardStandard.dat", "test_beatmap/beatmapv2.dat"),
+            ("ExpertStandard.dat", "test_beatmap/beatmapv3.dat"),
+            ("ExpertPlusStandard.dat", expert_plus),
+        ];
+        for (name, path) in files {
+            writer.start_file(name, Default::default()).unwrap();
+            writer.write_all(&std::fs::read(path).unwrap()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn reading_level_zip() -> Result<()> {
+        let level = Level::from_reader(Cursor::new(test_level_zip("test_beatmap/beatmapv3.dat")))?;
+        assert_eq!(level.beatmaps.len(), 3);
+        assert!(level.beatmap("Standard", Difficulty::Hard).is_some());
+
+        let files = ["test_beatmap/info.dat", "test_beatmap/beatmapv2.dat", "test_beatmap/beatmapv3.dat", "test_beatmap/beatmapv3.dat"]
+            .map(|x| std::fs::read(x).unwrap());
+        assert_eq!(level.source_hash, Some(Level::hash_bytes(&files[0], &files[1..])));
+        assert_eq!(Level::hash_bytes(b"abc", &[b""]), "A9993E364706816ABA3E25717850C26C9CD0D89D");
+
+        let mut writer = zip::ZipWriter::new_append(Cursor::new(test_level_zip("test_beatmap/beatmapv3.dat")))?;
+        writer.start_file("BPMInfo.dat", Default::default())?;
+        writer.write_all(b"{")?;
+        let level = Level::from_reader(writer.finish()?)?;
+        assert_eq!((level.audio_data, level.assets.len()), (None, 1));
+
+        let err = Level::from_reader(Cursor::new(test_level_zip("test_beatmap/info.dat"))).unwrap_err();
+        assert!(matches!(err, Error::DifficultyParsingFailed { filename, .. } if filename == "ExpertPlusStandard.dat"));
+        Ok(())
+    }
+
+    #[test]
+    fn reading_info_v4() -> Result<()> {
+        let mut meta = BeatmapSetMeta::read_from_file("test_beatmap/info.dat")?;
+        meta.difficulty_sets[0].beatmaps[2].lightshow_filename = Some("Lightshow.dat".to_string());
+        let (data, warnings) = meta.write_to_string_v4()?;
+        let lightshow_missing = ["HardStandard.dat", "ExpertStandard.dat"].map(|x| ConversionWarning::LightshowFilenameMissing { filename: x.to_string() });
+        assert_eq!(warnings, [vec![ConversionWarning::AudioDataMissing, ConversionWarning::SongDurationMissing], lightshow_missing.to_vec()].concat());
+        assert!(matches!(InfoFile::read_from_str(&data)?, InfoFile::V4(_)));
+
+        let mut all_directions = meta.clone();
+        all_directions.all_directions_environment_name = Some("GlassDesertEnvironment".to_string());
+        all_directions.difficulty_sets.push(DifficultySet { game_mode: "360Degree".to_string(), ..meta.difficulty_sets[0].clone() });
+        all_directions.color_schemes.push(ColorScheme::default());
+        all_directions.difficulty_sets[0].beatmaps[0].color_scheme = Some(0);
+        let (info, warnings) = all_directions.to_v4_info();
+        assert!(warnings.contains(&ConversionWarning::ColorSchemeDefaulted { filename: "ExpertStandard.dat".to_string() }));
+        assert_eq!(warnings.iter().filter(|x| matches!(x, ConversionWarning::ColorSchemeDefaulted { .. })).count(), 5);
+        let read = BeatmapSetMeta::from(info);
+        assert_eq!(read.all_directions_environment_name.as_deref(), Some("GlassDesertEnvironment"));
+        assert_eq!(read.difficulty_sets[1].beatmaps[0].environment_name.as_deref(), Some("GlassDesertEnvironment"));
+        assert_eq!(read.difficulty_sets[0].beatmaps[0].environment_name, Some(meta.environment_name.clone()));
+        let read = BeatmapSetMeta::read_from_str(&data)?;
+        assert_eq!((&read.song_name, &read.map_author, &read.environment_name), (&meta.song_name, &meta.map_author, &meta.environment_name));
+        assert_eq!(read.difficulty_sets[0].beatmaps[0].mappers, vec![meta.map_author.clone()]);
+        assert_eq!(read.difficulty_sets[0].beatmaps.len(), 3);
+        assert!(BeatmapSetMeta::read_from_str(&read.write_to_string()?)?.version.starts_with('2'));
+
+        let (beatmap, lightshow, _) = Beatmap::read_from_file("test_beatmap/beatmapv3.dat")?.write_to_strings_v4()?;
+        let mut writer = zip::ZipWriter::new(Cursor::new(vec![]));
+        let files = [
+            ("Info.dat", data),
+            ("HardStandard.dat", std::fs::read_to_string("test_beatmap/beatmapv2.dat")?),
+            ("ExpertStandard.dat", std::fs::read_to_string("test_beatmap/beatmapv3.dat")?),
+            ("ExpertPlusStandard.dat", beatmap),
+            ("Lightshow.dat", lightshow),
+        ];
+        for (name, data) in files {
+            writer.start_file(name, Default::default())?;
+            writer.write_all(data.as_bytes())?;
+        }
+        let mut level = Level::from_reader(writer.finish()?)?;
+        assert!(level.assets.is_empty());
+        let expert_plus = level.beatmap("Standard", Difficulty::ExpertPlus).unwrap();
+        assert!(expert_plus.events.iter().any(|x| matches!(x, Event::BasicEvent(_))));
+        let event_count = expert_plus.events.len();
+
+        level.assets.insert(level.meta.song_filename.clone(), vec![]);
+        level.assets.insert(level.meta.cover_image_filename.clone(), vec![]);
+        level.meta.song_preview_filename = Some("preview.ogg".to_string());
+        level.assets.insert("preview.ogg".to_string(), vec![1]);
+        assert!(matches!(level.to_writer(Cursor::new(vec![])), Err(Error::MissingFile(_))));
+        level.meta.audio_data_filename = Some("AudioData.dat".to_string());
+        level.audio_data = Some(AudioData::read_from_str(r#"{"version":"4.0.0","songChecksum":"","songSampleCount":441000,"songFrequency":44100,"bpmData":[],"lufsData":[]}"#)?);
+        let mut without_lightshow = level.clone();
+        without_lightshow.meta.difficulty_sets[0].beatmaps[2].lightshow_filename = None;
+        assert!(matches!(without_lightshow.to_writer(Cursor::new(vec![])), Err(Error::MissingLightshow(name)) if name == "ExpertPlusStandard.dat"));
+
+        let mut data = Cursor::new(vec![]);
+        assert_eq!(level.to_writer(&mut data)?, lightshow_missing.to_vec());
+        let mut archive = zip::ZipArchive::new(data.clone())?;
+        let mut read_file = |name: &str| {
+            let mut file = vec![];
+            std::io::Read::read_to_end(&mut archive.by_name(name).unwrap(), &mut file).unwrap();
+            file
+        };
+        // SongCore hashes a v4 level's audio data file and lightshows too
+        let hashed = ["AudioData.dat", "HardStandard.dat", "ExpertStandard.dat", "ExpertPlusStandard.dat", "Lightshow.dat"].map(&mut read_file);
+        let expected_hash = Level::hash_bytes(&read_file("Info.dat"), &hashed);
+        let written = Level::from_reader(data)?;
+        assert_eq!(written.source_hash, Some(expected_hash));
+        assert!(written.meta.version.starts_with('4'));
+        assert_eq!(written.audio_data, level.audio_data);
+        assert_eq!(written.assets["preview.ogg"], vec![1]);
+        assert_eq!(written.source_hash, Some(level.hash()?));
+        let written_expert_plus = written.beatmap("Standard", Difficulty::ExpertPlus).unwrap();
+        assert!(written_expert_plus.version.starts_with('4'));
+        assert_eq!(written_expert_plus.events.len(), event_count);
+        assert!(written.beatmap("Standard", Difficulty::Hard).unwrap().version.starts_with('2'));
+        Ok(())
+    }
+
+    #[test]
+    fn reading_songcore_data() -> Result<()> {
+        let mut meta = BeatmapSetMeta::read_from_file("test_beatmap/info.dat")?;
+        let editors = meta.editors().unwrap();
+        assert_eq!(editors.last_edited_by.as_deref(), Some("ChroMapper"));
+        assert!(editors.editors.contains_key("ChroMapper"));
+        meta.set_contributors(vec![Contributor { role: "Lighter".to_string(), name: "Someone".to_string(), icon_path: String::new() }]);
+
+        let hard = &mut meta.difficulty_sets[0].beatmaps[0];
+        let colors = hard.colors();
+        assert_eq!(colors.left, Some([1.0, 0.521, 0.921, 1.0]));
+        assert!(colors.env_white.is_none());
+        assert!(!hard.requires("Noodle Extensions"));
+        hard.set_requirements(vec!["Noodle Extensions".to_string()]);
+        hard.set_difficulty_label(Some("Hard but not really"));
+        hard.set_colors(Default::default());
+        assert!(hard.custom_data.contains_key("_requirements"));
+
+        let read = BeatmapSetMeta::read_from_str(&meta.write_to_string()?)?;
+        assert_eq!(read.contributors()[0].role, "Lighter");
+        let hard = &read.difficulty_sets[0].beatmaps[0];
+        assert!(hard.requires("Noodle Extensions"));
+        assert_eq!(hard.difficulty_label(), Some("Hard but not really"));
+        assert!(hard.colors().is_empty());
+
+        let mut read = BeatmapSetMeta::read_from_str(&meta.write_to_string_v4()?.0)?;
+        assert_eq!(read.custom_data["contributors"][0]["role"], json!("Lighter"));
+        assert!(read.difficulty_sets[0].beatmaps[0].custom_data.contains_key("difficultyLabel"));
+        read.set_custom_environment(Some("Platform"));
+        read.set_contributors(vec![Contributor { role: "Mapper".to_string(), name: "Someone".to_string(), icon_path: String::new() }]);
+        assert_eq!(read.custom_data["customEnvironment"], json!("Platform"));
+        assert_eq!(read.custom_data["contributors"][0]["role"], json!("Mapper"));
+        assert!(BeatmapSetMeta::read_from_str(&read.write_to_string()?)?.custom_data.contains_key("_customEnvironment"));
+        Ok(())
+    }
+
+    #[test]
+    fn resolving_color_schemes() -> Result<()> {
+        let mut meta = BeatmapSetMeta::read_from_file("test_beatmap/info.dat")?;
+        let scheme = meta.color_scheme(&meta.difficulty_sets[0].beatmaps[0]);
+        assert_eq!(scheme.saber_left, [1.0, 0.521, 0.921, 1.0]);
+        assert_eq!(scheme.env_right_boost, [1.0, 0.76, 0.07, 1.0]);
+        assert!(scheme.override_notes && scheme.override_lights);
+
+        let light = LightState { color: LightColor::Blue, brightness: 0.5, boost: false, blend_to: Some((LightColor::Red, 1.0)) };
+        assert_eq!(light.rgba(&scheme), [0.639 * 0.5, 0.28 * 0.5, 0.926 * 0.5, 1.0]);
+        assert_eq!(ColorScheme::default().light_color(LightColor::White, true), [1.0; 4]);
+
+        let custom = ColorScheme { name: "Custom".to_string(), override_notes: true, saber_left: [1.0, 0.0, 0.0, 1.0], ..Default::default() };
+        meta.color_schemes.push(custom.clone());
+        let beatmap = &mut meta.difficulty_sets[0].beatmaps[1];
+        beatmap.custom_data.clear();
+        beatmap.color_scheme = Some(0);
+        let read = BeatmapSetMeta::read_from_str(&meta.write_to_string_v4()?.0)?;
+        let scheme = &read.color_schemes[0];
+        assert_eq!((&scheme.name, scheme.saber_left, scheme.env_white), (&custom.name, custom.saber_left, None));
+        assert!((scheme.saber_right[2] - custom.saber_right[2]).abs() < 1.0 / 255.0);
+        assert_eq!(read.color_scheme(&read.difficulty_sets[0].beatmaps[1]).saber_left, [1.0, 0.0, 0.0, 1.0]);
+        Ok(())
+    }
+
+    #[test]
+    fn computing_stats() -> Result<()> {
+        let meta = BeatmapSetMeta::read_from_file("test_beatmap/info.dat")?;
+        let beatmap = Beatmap::read_from_file("test_beatmap/beatmapv3.dat")?;
+        let stats = Stats::new(&beatmap, &Timing::from_beatmap(&meta, &beatmap));
+        assert_eq!(stats.notes, beatmap.events.iter().filter(|x| matches!(x, Event::Note(_))).count());
+        assert_eq!(stats.note_count(Some(NoteColor::Red)) + stats.note_count(Some(NoteColor::Blue)), stats.notes);
+        assert!(stats.peak_nps(1.0).0 >= stats.nps());
+        let total: f64 = stats.density(None, 2.0).iter().map(|x| x * 2.0).sum();
+        assert_eq!(total as usize, stats.notes);
+
+        let notes = [0.0, 1.0, 1.5, 1.75, 4.0].map(|beat| Event::Note(Box::new(Note { beat, x: 0, y: 0, color: NoteColor::Red, direction: Direction::Any, angle_offset: 0.0, custom_data: HashMap::new(), extras: HashMap::new() })));
+        let beatmap = Beatmap { events: notes.to_vec(), ..Beatmap::read_from_file("test_beatmap/beatmapv3.dat")? };
+        let events = [BPMEvent { beat: 2.0, value: 60.0, extras: HashMap::new() }];
+        let stats = Stats::new(&beatmap, &Timing::new(120.0, 0.0, &events));
+        assert_eq!(stats.note_span(), 3.0);
+        assert_eq!(stats.peak_nps(1.0), (4.0, 0.0));
+        assert_eq!(stats.density(Some(NoteColor::Red), 1.0), vec![4.0, 0.0, 0.0, 1.0]);
+        assert_eq!(stats.section_density(Some(NoteColor::Blue), &[0.0, 3.0]), vec![0.0]);
+        Ok(())
+    }
+
+    #[test]
+    fn analyzing_parity() -> Result<()> {
+        let beatmap = Beatmap::read_from_file("test_beatmap/beatmapv3.dat")?;
+        let timing = Timing::new(120.0, 0.0, &[]);
+        assert!(!analyze(&beatmap, &timing).swings.is_empty());
+
+        let note = |beat: f64, x: i32, color: NoteColor, direction: Direction| Event::Note(Box::new(Note { beat, x, y: 1, color, direction, angle_offset: 0.0, custom_data: HashMap::new(), extras: HashMap::new() }));
+        let mut events: Vec<Event> = [(0.0, Direction::Down), (1.0, Direction::Up), (2.0, Direction::Down), (2.5, Direction::Down), (2.75, Direction::DownLeft),
+            (4.0, Direction::Up), (5.0, Direction::Down), (7.0, Direction::Down), (8.0, Direction::Up), (9.0, Direction::Up), (10.0, Direction::Down), (10.5, Direction::Any)]
+            .into_iter().map(|(beat, direction)| note(beat, 1, NoteColor::Red, direction)).collect();
+        events.push(note(10.0, 0, NoteColor::Red, Direction::Down));
+        events.push(note(0.0, 2, NoteColor::Blue, Direction::Up));
+        events.push(Event::Bomb(Box::new(Bomb { beat: 8.5, x: 1, y: 0, custom_data: HashMap::new(), extras: HashMap::new() })));
+        events.extend([note(1.0, 2, NoteColor::Blue, Direction::Down), note(2.0, 2, NoteColor::Blue, Direction::Down), note(3.0, 2, NoteColor::Blue, Direction::Any)]);
+        events.push(Event::Bomb(Box::new(Bomb { beat: 0.5, x: 3, y: 1, custom_data: HashMap::new(), extras: HashMap::new() })));
+        events.push(Event::Bomb(Box::new(Bomb { beat: 10.25, x: 1, y: 1, custom_data: HashMap::new(), extras: HashMap::new() })));
+        events.push(Event::Slider(Box::new(Slider {
+            head_beat: 2.0, color: NoteColor::Blue, head_x: 2, head_y: 1, head_direction: Direction::Down, head_bulge: 1.0,
+            tail_beat: 3.0, tail_x: 2, tail_y: 1, tail_direction: Direction::Down, tail_bulge: 1.0,
+            special_curving: SliderMidAnchorMode::Straight, custom_data: HashMap::new(), extras: HashMap::new(),
+        })));
+        let analysis = analyze(&Beatmap { events, ..beatmap }, &timing);
+
+        let red: Vec<Parity> = analysis.swings.iter().filter(|x| x.color == NoteColor::Red).map(|x| x.parity).collect();
+        assert_eq!(red.len(), 12);
+        assert_eq!(&red[..4], [Parity::Forehand, Parity::Backhand, Parity::Forehand, Parity::Forehand]);
+        assert_eq!(red[11], red[10]);
+        assert_eq!(analysis.swings.iter().find(|x| x.color == NoteColor::Blue).unwrap().parity, Parity::Backhand);
+        let blue: Vec<Parity> = analysis.swings.iter().filter(|x| x.color == NoteColor::Blue).map(|x| x.parity).collect();
+        // The bomb next to the blue saber doesn't stop it from alternating
+        assert_eq!(blue, [Parity::Backhand, Parity::Forehand, Parity::Forehand, Parity::Forehand]);
+        assert_eq!(analysis.warnings, vec![
+            ParityWarning::Reset { beat: 2.0, color: NoteColor::Blue },
+            ParityWarning::DoubleDirectional { beat: 2.5, color: NoteColor::Red },
+            ParityWarning::ParityBreak { beat: 2.75, color: NoteColor::Red },
+            ParityWarning::Reset { beat: 3.0, color: NoteColor::Blue },
+            ParityWarning::Reset { beat: 7.0, color: NoteColor::Red },
+            ParityWarning::BombReset { beat: 9.0, color: NoteColor::Red },
+            ParityWarning::BombReset { beat: 10.5, color: NoteColor::Red },
+        ]);
+        Ok(())
+    }
+
+    #[test]
+    fn detecting_vision_blocks() -> Result<()> {
+        assert_eq!(half_jump_duration(10.0, 0.0, 120.0), 2.0);
+        assert_eq!(half_jump_duration(10.0, -5.0, 120.0), 0.25);
+
+        let mut meta = BeatmapSetMeta::read_from_file("test_beatmap/info.dat")?.difficulty_sets[0].beatmaps[0].clone();
+        (meta.note_jump_speed, meta.note_jump_start_beat_offset) = (10.0, 0.0);
+        let note = |beat: f64, x: i32| Event::Note(Box::new(Note { beat, x, y: 1, color: NoteColor::Red, direction: Direction::Down, angle_offset: 0.0, custom_data: HashMap::new(), extras: HashMap::new() }));
+        let mut events = vec![note(0.0, 1), note(0.0, 0), note(1.0, 1), note(1.5, 3), note(2.5, 0), note(4.25, 2), note(5.0, 3), note(6.2, 0)];
+        events.push(Event::Obstacle(Box::new(Obstacle { beat: 4.0, x: 0, y: 0, duration: 0.5, width: 4.0, height: 5.0, custom_data: HashMap::new(), extras: HashMap::new() })));
+        let beatmap = Beatmap { events, ..Beatmap::read_from_file("test_beatmap/beatmapv3.dat")? };
+        let blocks = vision_blocks(&beatmap, &meta, 120.0, &Timing::new(120.0, 0.0, &[]));
+        assert_eq!(blocks.len(), 2);
+        assert_eq!((blocks[0].blocker, &blocks[0].hidden_beats, blocks[0].hidden_for), (Blocker::Note, &vec![1.0], 0.5));
+        assert_eq!((blocks[1].blocker, &blocks[1].hidden_beats, blocks[1].hidden_for), (Blocker::Obstacle, &vec![4.25, 5.0, 6.2], 1.0));
+        Ok(())
+    }
+
+    #[test]
+    fn calculating_jump() -> Result<()> {
+        assert_eq!(jump_distance(10.0, 0.0, 120.0), 20.0);
+        assert_eq!(reaction_time(10.0, 0.0, 120.0), 1000.0);
+        assert_eq!(half_jump_duration(20.0, 0.0, 120.0), 1.0);
+        assert_eq!(offset_for_jump_distance(30.0, 10.0, 120.0), Some(1.0));
+        assert_eq!(offset_for_jump_distance(1.0, 10.0, 120.0), None);
+
+        let meta = BeatmapSetMeta::read_from_file("test_beatmap/info.dat")?;
+        let mut beatmap = meta.difficulty_sets[0].beatmaps[1].clone();
+        let offset = beatmap.set_jump_distance(24.0, meta.bpm).unwrap();
+        assert_eq!(beatmap.note_jump_start_beat_offset, offset);
+        assert!((beatmap.jump_distance(meta.bpm) - 24.0).abs() < 1e-9);
+        assert!((beatmap.reaction_time(meta.bpm) - 24.0 / beatmap.note_jump_speed / 2.0 * 1000.0).abs() < 1e-6);
+        Ok(())
+    }
+
+    #[test]
+    fn writing_level_zip() -> Result<()> {
+        let mut level = Level::from_reader(Cursor::new(test_level_zip("test_beatmap/beatmapv3.dat")))?;
+        assert!(matches!(level.to_writer(Cursor::new(vec![])), Err(Error::MissingFile(name)) if name == "song.egg"));
+
+        level.assets.insert("song.egg".to_string(), vec![1, 2, 3]);
+        level.assets.insert("cover.jpg".to_string(), vec![4, 5, 6]);
+        level.assets.insert("autosave.dat".to_string(), vec![]);
+        assert_eq!(level.stray_files(), vec!["autosave.dat"]);
+
+        let mut data = Cursor::new(vec![]);
+        level.to_writer(&mut data)?;
+        let written = Level::from_reader(data)?;
+        assert_eq!(written.source_hash, Some(level.hash()?));
+        assert_eq!(written.beatmaps.len(), 3);
+        assert_eq!(written.assets.len(), 2);
+        assert_eq!(written.assets["song.egg"], vec![1, 2, 3]);
+        Ok(())
+    }
+
     #[ignore]
     #[test]
     fn reading_your_beatmap() -> Result<()> {
-        let file = File::open("test_beatmap.zip")?;
-        let reader = BufReader::new(file);
-        let mut archive = zip::ZipArchive::new(reader).unwrap();
-        let mut meta_file = vec![];
-        archive.by_name("Info.dat").unwrap().read_to_end(&mut meta_file).unwrap();
-        let meta = BeatmapSetMeta::read_from_str(&String::from_utf8_lossy(&meta_file.as_slice())).unwrap();
-
-        let filenames = meta.difficulty_sets
-            .into_iter()
-            .map(|x| x.beatmaps)
-            .flatten()
-            .map(|x| x.filename);
-        for filename in filenames {
-            let mut map_file = vec![];
-            archive.by_name(&filename).unwrap().read_to_end(&mut map_file).unwrap();
-            let _map = Beatmap::read_from_str(&String::from_utf8_lossy(&map_file.as_slice())).unwrap();
-        }
+        let _level = Level::open_zip("test_beatmap.zip")?;
         Ok(())
     }
 }
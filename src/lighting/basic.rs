@@ -0,0 +1,241 @@
+//! Simulation of basic (v2 style) lighting events, see [LightingState]
+
+use std::collections::HashMap;
+use crate::types::common::{BasicEventType, LightColor, LightValue};
+use crate::types::primary::{BasicEvent, ColorBoost, ColorScheme, Event};
+use crate::{Beatmap, Timing};
+
+/// How long a flash takes to settle to normal brightness, in seconds. Approximates the game
+pub const FLASH_DURATION: f64 = 0.6;
+/// How long a fade takes to turn the light off, in seconds. Approximates the game
+pub const FADE_DURATION: f64 = 1.5;
+/// Brightness a flash starts at, relative to the event's brightness
+pub const FLASH_BRIGHTNESS: f64 = 1.2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Effect {
+    Off,
+    On,
+    Flash,
+    Fade,
+    Transition,
+}
+
+fn decode_light_value(value: LightValue) -> (Effect, LightColor) {
+    match value {
+        LightValue::Off => (Effect::Off, LightColor::Blue),
+        LightValue::On(color) => (Effect::On, color),
+        LightValue::Flash(color) => (Effect::Flash, color),
+        LightValue::Fade(color) => (Effect::Fade, color),
+        LightValue::Transition(color) => (Effect::Transition, color),
+    }
+}
+
+/// What a light looks like at some moment
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightState {
+    pub color: LightColor,
+    /// 0 is off, 1 is normal. Flashes go above 1
+    pub brightness: f64,
+    /// Whether the boost color palette is in use
+    pub boost: bool,
+    /// Set during a transition: the color being blended into and how far along the blend is, 0 to 1
+    pub blend_to: Option<(LightColor, f64)>,
+}
+
+impl LightState {
+    pub fn is_off(&self) -> bool {
+        self.brightness <= 0.0
+    }
+
+    /// RGBA of the light in `scheme`, with blends applied and RGB scaled by brightness
+    pub fn rgba(&self, scheme: &ColorScheme) -> [f64; 4] {
+        let mut color = scheme.light_color(self.color, self.boost);
+        if let Some((to, progress)) = self.blend_to {
+            let to = scheme.light_color(to, self.boost);
+            color = std::array::from_fn(|i| color[i] + (to[i] - color[i]) * progress);
+        }
+        [color[0] * self.brightness, color[1] * self.brightness, color[2] * self.brightness, color[3]]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct LightKeyframe {
+    seconds: f64,
+    effect: Effect,
+    color: LightColor,
+    brightness: f64,
+}
+
+impl LightKeyframe {
+    fn brightness_at(&self, seconds: f64) -> f64 {
+        let elapsed = seconds - self.seconds;
+        match self.effect {
+            Effect::Off => 0.0,
+            Effect::On | Effect::Transition => self.brightness,
+            Effect::Flash => {
+                let progress = (elapsed / FLASH_DURATION).clamp(0.0, 1.0);
+                self.brightness * (FLASH_BRIGHTNESS + (1.0 - FLASH_BRIGHTNESS) * progress)
+            },
+            Effect::Fade => {
+                let progress = (elapsed / FADE_DURATION).clamp(0.0, 1.0);
+                self.brightness * FLASH_BRIGHTNESS * (1.0 - progress)
+            },
+        }
+    }
+
+    /// When the light goes dark after this keyframe, if it does on its own
+    fn dark_from(&self) -> Option<f64> {
+        match self.effect {
+            Effect::Off => Some(self.seconds),
+            Effect::Fade => Some(self.seconds + FADE_DURATION),
+            _ if self.brightness <= 0.0 => Some(self.seconds),
+            _ => None,
+        }
+    }
+}
+
+/// Interprets the basic events and color boosts of a beatmap, so the lights can be queried at any moment. Times are in seconds, as given by the [Timing]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightingState {
+    lights: HashMap<BasicEventType, Vec<LightKeyframe>>,
+    boosts: Vec<(f64, bool)>,
+    laser_speeds: HashMap<BasicEventType, Vec<(f64, f64)>>,
+    ring_rotations: Vec<f64>,
+    ring_zooms: Vec<f64>,
+    scheme: ColorScheme,
+}
+
+impl LightingState {
+    /// Colors come from `scheme`, see [BeatmapSetMeta::color_scheme](crate::BeatmapSetMeta::color_scheme)
+    pub fn new(beatmap: &Beatmap, timing: &Timing, scheme: &ColorScheme) -> Self {
+        let mut state = Self {
+            lights: HashMap::new(),
+            boosts: vec![],
+            laser_speeds: HashMap::new(),
+            ring_rotations: vec![],
+            ring_zooms: vec![],
+            scheme: scheme.clone(),
+        };
+        let mut boosts: Vec<&ColorBoost> = vec![];
+        let mut events: Vec<&BasicEvent> = vec![];
+        for event in &beatmap.events {
+            match event {
+                Event::ColorBoost(boost) => boosts.push(boost),
+                Event::BasicEvent(event) => events.push(event),
+                _ => {}
+            }
+        }
+        boosts.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+        events.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+
+        state.boosts = boosts.iter().map(|x| (timing.beat_to_seconds(x.beat), x.enable)).collect();
+        for event in events {
+            let seconds = timing.beat_to_seconds(event.beat);
+            match event.event_type() {
+                Ok(kind) if kind.is_light() => if let Ok(value) = event.light_value() {
+                    let (effect, color) = decode_light_value(value);
+                    let brightness = event.float_value.unwrap_or(1.0);
+                    state.lights.entry(kind).or_default().push(LightKeyframe { seconds, effect, color, brightness });
+                },
+                Ok(kind @ (BasicEventType::LeftLaserSpeed | BasicEventType::RightLaserSpeed)) => {
+                    state.laser_speeds.entry(kind).or_default().push((seconds, event.value as f64));
+                },
+                Ok(BasicEventType::RingSpin) => state.ring_rotations.push(seconds),
+                Ok(BasicEventType::RingZoom) => state.ring_zooms.push(seconds),
+                _ => {}
+            }
+        }
+        state
+    }
+
+    /// Whether the boost colors are in use at `seconds`
+    pub fn boost_at(&self, seconds: f64) -> bool {
+        self.boosts.iter().take_while(|(time, _)| *time <= seconds).last().map(|(_, enable)| *enable).unwrap_or(false)
+    }
+
+    /// State of the lights of a lane (e.g. [BasicEventType::BackLasers]) at `seconds`. `None` if the lane hasn't received any events yet
+    pub fn light_at(&self, kind: BasicEventType, seconds: f64) -> Option<LightState> {
+        let keyframes = self.lights.get(&kind)?;
+        let index = keyframes.iter().rposition(|x| x.seconds <= seconds)?;
+        let current = &keyframes[index];
+        let mut state = LightState {
+            color: current.color,
+            brightness: current.brightness_at(seconds),
+            boost: self.boost_at(seconds),
+            blend_to: None,
+        };
+        if let Some(next) = keyframes.get(index + 1).filter(|x| x.effect == Effect::Transition) {
+            let span = next.seconds - current.seconds;
+            let progress = if span > 0.0 { (seconds - current.seconds) / span } else { 1.0 };
+            let from = if current.effect == Effect::Off { 0.0 } else { state.brightness };
+            state.brightness = from + (next.brightness - from) * progress;
+            if current.effect == Effect::Off {
+                state.color = next.color;
+            } else if next.color != current.color {
+                state.blend_to = Some((next.color, progress));
+            }
+        }
+        Some(state)
+    }
+
+    /// RGBA of the lights of a lane at `seconds`, see [LightState::rgba]
+    pub fn rgba_at(&self, kind: BasicEventType, seconds: f64) -> Option<[f64; 4]> {
+        Some(self.light_at(kind, seconds)?.rgba(&self.scheme))
+    }
+
+    /// Rotation speed of a laser lane ([BasicEventType::LeftLaserSpeed] or [BasicEventType::RightLaserSpeed]) at `seconds`
+    pub fn laser_speed_at(&self, kind: BasicEventType, seconds: f64) -> f64 {
+        self.laser_speeds.get(&kind)
+            .and_then(|x| x.iter().take_while(|(time, _)| *time <= seconds).last())
+            .map(|(_, speed)| *speed)
+            .unwrap_or(0.0)
+    }
+
+    /// How many times the rings were told to rotate up to `seconds`
+    pub fn ring_rotations(&self, seconds: f64) -> usize {
+        self.ring_rotations.iter().take_while(|x| **x <= seconds).count()
+    }
+
+    /// Whether the rings are zoomed in at `seconds`. Every zoom event toggles it
+    pub fn rings_zoomed(&self, seconds: f64) -> bool {
+        self.ring_zooms.iter().take_while(|x| **x <= seconds).count() % 2 == 1
+    }
+
+    /// Lanes that received at least one light event
+    pub fn light_lanes(&self) -> Vec<BasicEventType> {
+        let mut lanes: Vec<BasicEventType> = self.lights.keys().copied().collect();
+        lanes.sort_by_key(|x| *x as i32);
+        lanes
+    }
+
+    /// Stretches (start and end, in seconds) during which a lane stays dark for at least `min_duration`.
+    /// Time before the first event (from 0) counts as dark, and a lane left off at the end gives a stretch ending at infinity
+    pub fn dark_stretches(&self, kind: BasicEventType, min_duration: f64) -> Vec<(f64, f64)> {
+        let keyframes = self.lights.get(&kind).map(Vec::as_slice).unwrap_or_default();
+        let mut stretches = vec![];
+        let mut dark_since = Some(0.0);
+        for (index, keyframe) in keyframes.iter().enumerate() {
+            let next = keyframes.get(index + 1);
+            // A transition brightens the light from the previous event onwards
+            let transitions_in = next.map(|x| x.effect == Effect::Transition && x.brightness > 0.0).unwrap_or(false);
+            let dark_from = keyframe.dark_from();
+            if dark_from != Some(keyframe.seconds) || transitions_in {
+                if let Some(since) = dark_since.take() {
+                    stretches.push((since, keyframe.seconds));
+                }
+                if transitions_in {
+                    continue;
+                }
+            }
+            if dark_since.is_none() {
+                dark_since = dark_from.filter(|x| next.map(|next| *x < next.seconds).unwrap_or(true));
+            }
+        }
+        if let Some(since) = dark_since {
+            stretches.push((since, f64::INFINITY));
+        }
+        stretches.retain(|(start, end)| end - start >= min_duration);
+        stretches
+    }
+}
@@ -0,0 +1,284 @@
+//! Evaluation of v3 light event boxes into per-light keyframes
+
+use crate::types::common::{Axis, BoxFilterOrdering, DistributionKind, Easing, LightColor, LimitKind, RotationBehaviour, RotationDirection, TransitionKind};
+use crate::types::lightning::{BoxFilter, BoxFilterSettings, LightEventBox, LightEventLane, LightEvents};
+use crate::environment::Environment;
+use crate::types::primary::{ColorScheme, Event};
+use crate::Beatmap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorKeyframe {
+    pub beat: f64,
+    pub transition_kind: TransitionKind,
+    pub color: LightColor,
+    pub brightness: f64,
+    /// Strobe frequency, 0 means no strobe
+    pub frequency: i32,
+    /// RGBA the keyframe sets in the scheme the boxes were evaluated with, with RGB scaled by brightness. Boost colors are used if a color boost is on at the keyframe's beat
+    pub rgba: [f64; 4],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RotationKeyframe {
+    pub beat: f64,
+    pub axis: Axis,
+    pub behaviour: RotationBehaviour,
+    pub easing: Easing,
+    pub loops: i32,
+    pub angle: f64,
+    pub direction: RotationDirection,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranslationKeyframe {
+    pub beat: f64,
+    pub axis: Axis,
+    pub behaviour: RotationBehaviour,
+    pub easing: Easing,
+    pub offset: f64,
+}
+
+impl ColorKeyframe {
+    fn apply_scheme(&mut self, scheme: &ColorScheme, boost: bool) {
+        let color = scheme.light_color(self.color, boost);
+        self.rgba = [color[0] * self.brightness, color[1] * self.brightness, color[2] * self.brightness, color[3]];
+    }
+}
+
+/// Everything that happens to a single light, sorted by beat
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LightTimeline {
+    pub color: Vec<ColorKeyframe>,
+    pub rotation: Vec<RotationKeyframe>,
+    pub translation: Vec<TranslationKeyframe>,
+}
+
+impl Easing {
+    pub fn apply(&self, p: f64) -> f64 {
+        match self {
+            Easing::None | Easing::Linear => p,
+            Easing::EaseInQuad => p * p,
+            Easing::EaseOutQuad => -(p * (p - 2.0)),
+            Easing::EaseInOutQuad => if p < 0.5 { 2.0 * p * p } else { -2.0 * p * p + 4.0 * p - 1.0 },
+        }
+    }
+}
+
+/// .NET's seeded `System.Random` (Knuth's subtractive generator), which the game uses for the random filter orderings
+struct Random {
+    seeds: [i32; 56],
+    next: usize,
+    next_p: usize,
+}
+
+impl Random {
+    fn new(seed: i32) -> Self {
+        let mut seeds = [0; 56];
+        let mut mj = 161_803_398 - if seed == i32::MIN { i32::MAX } else { seed.abs() };
+        seeds[55] = mj;
+        let mut mk = 1;
+        for i in 1..55 {
+            let ii = 21 * i % 55;
+            seeds[ii] = mk;
+            mk = mj - mk;
+            if mk < 0 {
+                mk += i32::MAX;
+            }
+            mj = seeds[ii];
+        }
+        for _ in 1..5 {
+            for i in 1..56 {
+                seeds[i] -= seeds[1 + (i + 30) % 55];
+                if seeds[i] < 0 {
+                    seeds[i] += i32::MAX;
+                }
+            }
+        }
+        Self { seeds, next: 0, next_p: 21 }
+    }
+
+    fn sample(&mut self) -> f64 {
+        self.next = if self.next >= 55 { 1 } else { self.next + 1 };
+        self.next_p = if self.next_p >= 55 { 1 } else { self.next_p + 1 };
+        let mut result = self.seeds[self.next] - self.seeds[self.next_p];
+        if result == i32::MAX {
+            result -= 1;
+        }
+        if result < 0 {
+            result += i32::MAX;
+        }
+        self.seeds[self.next] = result;
+        result as f64 / i32::MAX as f64
+    }
+
+    /// `Next(maxValue)`, in `0..bound`
+    fn next(&mut self, bound: usize) -> usize {
+        (self.sample() * bound as f64) as usize
+    }
+}
+
+impl BoxFilter {
+    /// Picks the lights affected by the filter, in distribution order. Each element is a chunk of light IDs that act together.
+    /// The second value is the element count the distributions are spread over
+    pub fn apply(&self, light_count: usize) -> (Vec<Vec<usize>>, usize) {
+        let mut elements: Vec<Vec<usize>> = if self.chunks > 0 {
+            let chunks = self.chunks as usize;
+            (0..chunks.min(light_count)).map(|chunk| {
+                (0..light_count).filter(|id| id * chunks.min(light_count) / light_count == chunk).collect()
+            }).collect()
+        } else {
+            (0..light_count).map(|id| vec![id]).collect()
+        };
+        if self.reverse {
+            elements.reverse();
+        }
+
+        let count = elements.len();
+        let mut selected: Vec<Vec<usize>> = match self.settings {
+            BoxFilterSettings::Sections { count: sections, index } => {
+                let sections = sections.max(1) as usize;
+                let index = index.max(0) as usize;
+                let start = count * index / sections;
+                let end = (count * (index + 1) / sections).min(count);
+                elements.drain(start.min(end)..end).collect()
+            },
+            BoxFilterSettings::StepAndOffset { start, skip } => {
+                elements.into_iter().skip(start.max(0) as usize).step_by(skip.max(1) as usize).collect()
+            }
+        };
+
+        let mut random = Random::new(self.random_seed);
+        match self.ordering {
+            BoxFilterOrdering::Random => for i in (1..selected.len()).rev() {
+                selected.swap(i, random.next(i + 1));
+            },
+            BoxFilterOrdering::RandomStartingIndex => {
+                let len = selected.len();
+                selected.rotate_left(random.next(len));
+            },
+            BoxFilterOrdering::Standard1 | BoxFilterOrdering::Standard2 => {}
+        }
+
+        let unlimited_count = selected.len();
+        if self.limit > 0.0 && self.limit < 1.0 {
+            selected.truncate((unlimited_count as f64 * self.limit).ceil() as usize);
+        }
+        (selected, unlimited_count)
+    }
+}
+
+fn distribution(kind: DistributionKind, amount: f64, index: usize, count: usize, easing: Option<Easing>) -> f64 {
+    match kind {
+        DistributionKind::Wave => {
+            let progress = if count > 0 { index as f64 / count as f64 } else { 0.0 };
+            amount * easing.map(|x| x.apply(progress)).unwrap_or(progress)
+        },
+        DistributionKind::Step => amount * index as f64,
+    }
+}
+
+impl LightEventLane {
+    fn evaluate(&self, beat: f64, timelines: &mut [LightTimeline]) {
+        let (elements, unlimited_count) = self.filter.apply(timelines.len());
+        let limit_affects_duration = matches!(self.filter.limit_kind, LimitKind::SectionsDuration | LimitKind::SectionsDurationBrightness);
+        let limit_affects_value = matches!(self.filter.limit_kind, LimitKind::SectionsBrightness | LimitKind::SectionsDurationBrightness);
+        let beat_count = if limit_affects_duration { elements.len() } else { unlimited_count };
+        let value_count = if limit_affects_value { elements.len() } else { unlimited_count };
+        let axis = self.axis.unwrap_or(Axis::X);
+        let sign = if self.reverse == Some(true) { -1.0 } else { 1.0 };
+
+        for (index, ids) in elements.iter().enumerate() {
+            let start = beat + distribution(self.beat_dist_kind, self.beat_dist, index, beat_count, None);
+            let value_offset = distribution(self.dist_kind, self.dist, index, value_count, self.dist_easing);
+            for id in ids {
+                let timeline = &mut timelines[*id];
+                match &self.events {
+                    LightEvents::Color(events) => for (i, event) in events.iter().enumerate() {
+                        let offset = if i > 0 || self.dist_affects_first_event { value_offset } else { 0.0 };
+                        timeline.color.push(ColorKeyframe {
+                            beat: start + event.relative_beat,
+                            transition_kind: event.transition_kind,
+                            color: event.color,
+                            brightness: event.brightness + offset,
+                            frequency: event.frequency,
+                            rgba: [0.0; 4],
+                        });
+                    },
+                    LightEvents::Rotation(events) => for (i, event) in events.iter().enumerate() {
+                        let offset = if i > 0 || self.dist_affects_first_event { value_offset } else { 0.0 };
+                        timeline.rotation.push(RotationKeyframe {
+                            beat: start + event.relative_beat,
+                            axis,
+                            behaviour: event.behaviour,
+                            easing: event.easing,
+                            loops: event.loops,
+                            angle: sign * (event.amount + offset),
+                            direction: event.direction,
+                        });
+                    },
+                    LightEvents::Translation(events) => for (i, event) in events.iter().enumerate() {
+                        let offset = if i > 0 || self.dist_affects_first_event { value_offset } else { 0.0 };
+                        timeline.translation.push(TranslationKeyframe {
+                            beat: start + event.relative_beat,
+                            axis,
+                            behaviour: event.rotation_behaviour,
+                            easing: event.easing,
+                            offset: sign * (event.amount + offset),
+                        });
+                    },
+                }
+            }
+        }
+    }
+}
+
+impl LightTimeline {
+    fn sort(&mut self) {
+        self.color.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+        self.rotation.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+        self.translation.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+    }
+}
+
+/// Evaluates boxes targeting a group of `light_count` lights, with colors from `scheme` (see [BeatmapSetMeta::color_scheme](crate::BeatmapSetMeta::color_scheme)).
+/// Boxes for other groups are ignored. The result is indexed by light ID
+pub fn evaluate<'a>(boxes: impl IntoIterator<Item = &'a LightEventBox>, group: i32, light_count: usize, scheme: &ColorScheme) -> Vec<LightTimeline> {
+    evaluate_with_boosts(boxes, group, light_count, scheme, &[])
+}
+
+/// `boosts` are the beats color boosts turn on or off at, sorted
+fn evaluate_with_boosts<'a>(boxes: impl IntoIterator<Item = &'a LightEventBox>, group: i32, light_count: usize, scheme: &ColorScheme, boosts: &[(f64, bool)]) -> Vec<LightTimeline> {
+    let mut timelines = vec![LightTimeline::default(); light_count];
+    for event_box in boxes.into_iter().filter(|x| x.group == group) {
+        for lane in &event_box.lanes {
+            lane.evaluate(event_box.beat, &mut timelines);
+        }
+    }
+    timelines.iter_mut().for_each(LightTimeline::sort);
+    for keyframe in timelines.iter_mut().flat_map(|x| &mut x.color) {
+        let boost = boosts.iter().take_while(|(beat, _)| *beat <= keyframe.beat).last().map(|(_, enable)| *enable).unwrap_or(false);
+        keyframe.apply_scheme(scheme, boost);
+    }
+    timelines
+}
+
+/// Like [evaluate], for all the light event boxes of a beatmap. Its color boosts decide which keyframes use the boost colors
+pub fn evaluate_beatmap(beatmap: &Beatmap, group: i32, light_count: usize, scheme: &ColorScheme) -> Vec<LightTimeline> {
+    let mut boosts = vec![];
+    let mut boxes = vec![];
+    for event in &beatmap.events {
+        match event {
+            Event::LightEventBox(event_box) => boxes.push(event_box.as_ref()),
+            Event::ColorBoost(boost) => boosts.push((boost.beat, boost.enable)),
+            _ => {}
+        }
+    }
+    boosts.sort_by(|a, b| a.0.total_cmp(&b.0));
+    evaluate_with_boosts(boxes, group, light_count, scheme, &boosts)
+}
+
+/// Like [evaluate_beatmap], with the light count of the group taken from the environment. `None` if the environment doesn't have the group
+pub fn evaluate_in_environment(beatmap: &Beatmap, environment: &Environment, group: i32, scheme: &ColorScheme) -> Option<Vec<LightTimeline>> {
+    let light_count = environment.light_group(group)?.light_count;
+    Some(evaluate_beatmap(beatmap, group, light_count, scheme))
+}
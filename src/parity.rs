@@ -0,0 +1,200 @@
+//! Forehand/backhand inference for each saber, see [analyze]
+//!
+//! Every swing is given the cut angle of its first arrow (0 is down, 180 is up, plus the angle offset), or of an arc starting or ending on it. A forehand
+//! points the saber at the cut angle and a backhand opposite to it, and the wrist is assumed to turn up to 90 degrees
+//! either way from a downward forehand. Sabers alternate, and when a swing doesn't fit the next parity it's flagged
+
+use crate::error::ParityWarning;
+use crate::types::common::{Direction, NoteColor};
+use crate::types::primary::{Bomb, Event, Note, Slider};
+use crate::{Beatmap, Timing};
+
+/// Notes of one color closer than this, in seconds, are hit by a single swing (stacks, windows, sliders)
+pub const SWING_GROUPING: f64 = 0.08;
+/// A saber needs at least this long, in seconds, to bring itself back up (or down) between two swings of the same parity
+pub const MIN_RESET_TIME: f64 = 0.5;
+/// Farthest the wrist turns from a downward forehand, in degrees
+const MAX_ROTATION: f64 = 90.0;
+/// Swings closer in angle than this, in degrees, are considered the same direction
+const SAME_DIRECTION: f64 = 45.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parity {
+    Forehand,
+    Backhand,
+}
+
+impl Parity {
+    pub fn flip(self) -> Self {
+        match self {
+            Parity::Forehand => Parity::Backhand,
+            Parity::Backhand => Parity::Forehand,
+        }
+    }
+}
+
+/// One or more notes of a color hit at once
+#[derive(Debug, Clone, PartialEq)]
+pub struct Swing {
+    pub color: NoteColor,
+    pub beat: f64,
+    /// In seconds
+    pub start: f64,
+    /// In seconds. Later than `start` for sliders and burst sliders
+    pub end: f64,
+    /// Cut angle in degrees, `None` for swings with only dot notes
+    pub angle: Option<f64>,
+    pub parity: Parity,
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParityAnalysis {
+    /// Swings of both sabers, sorted by beat
+    pub swings: Vec<Swing>,
+    pub warnings: Vec<ParityWarning>,
+}
+
+fn direction_angle(direction: Direction) -> Option<f64> {
+    match direction {
+        Direction::Down => Some(0.0),
+        Direction::DownRight => Some(45.0),
+        Direction::Right => Some(90.0),
+        Direction::UpRight => Some(135.0),
+        Direction::Up => Some(180.0),
+        Direction::UpLeft => Some(-135.0),
+        Direction::Left => Some(-90.0),
+        Direction::DownLeft => Some(-45.0),
+        Direction::Any => None,
+    }
+}
+
+/// Into (-180, 180]
+fn normalize(angle: f64) -> f64 {
+    let angle = angle.rem_euclid(360.0);
+    if angle > 180.0 { angle - 360.0 } else { angle }
+}
+
+/// Whether the wrist can make a cut at `angle` with `parity`
+fn fits(angle: Option<f64>, parity: Parity) -> bool {
+    let Some(angle) = angle else { return true };
+    let rotation = match parity {
+        Parity::Forehand => angle,
+        Parity::Backhand => angle - 180.0,
+    };
+    normalize(rotation).abs() <= MAX_ROTATION
+}
+
+/// Whether a bomb between two swings sits where the saber would go for the alternating swing, forcing it back instead
+fn forces_reset(bomb: &Bomb, previous: &Swing) -> bool {
+    let towards = match previous.parity {
+        // Having swung down, the saber would go back up through the rows above
+        Parity::Forehand => bomb.y >= previous.y,
+        Parity::Backhand => bomb.y <= previous.y,
+    };
+    towards && (bomb.x - previous.x).abs() <= 1
+}
+
+fn swings_of(color: NoteColor, notes: &[&Note], beatmap: &Beatmap, timing: &Timing) -> Vec<Swing> {
+    let mut swings: Vec<Swing> = vec![];
+    for note in notes.iter().filter(|x| x.color == color) {
+        let seconds = timing.beat_to_seconds(note.beat);
+        let angle = direction_angle(note.direction).map(|x| normalize(x + note.angle_offset));
+        match swings.last_mut() {
+            Some(swing) if seconds - swing.end <= SWING_GROUPING => {
+                swing.end = swing.end.max(seconds);
+                swing.angle = swing.angle.or(angle);
+            },
+            _ => swings.push(Swing { color, beat: note.beat, start: seconds, end: seconds, angle, parity: Parity::Forehand, x: note.x, y: note.y }),
+        }
+    }
+    let swing_at = |swings: &mut [Swing], beat: f64| {
+        let seconds = timing.beat_to_seconds(beat);
+        swings.iter_mut().position(|x| seconds >= x.start - SWING_GROUPING && seconds <= x.end + SWING_GROUPING)
+    };
+    for event in &beatmap.events {
+        match event {
+            // Burst sliders are hit in the swing of their head
+            Event::BurstSlider(burst) if burst.color == color => if let Some(index) = swing_at(&mut swings, burst.head_beat) {
+                swings[index].end = swings[index].end.max(timing.beat_to_seconds(burst.tail_beat));
+            },
+            // Arcs give their directions to the dots they start and end on
+            Event::Slider(arc) if arc.color == color => {
+                for (beat, direction) in [(arc.head_beat, arc.head_direction), (arc.tail_beat, arc.tail_direction)] {
+                    if let Some(index) = swing_at(&mut swings, beat) {
+                        swings[index].angle = swings[index].angle.or(direction_angle(direction));
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+    swings
+}
+
+/// Infers the parity of every swing and flags the spots where a saber can't alternate.
+/// Same parity swings far enough apart, or joined by an arc, are resets
+pub fn analyze(beatmap: &Beatmap, timing: &Timing) -> ParityAnalysis {
+    let mut notes: Vec<&Note> = vec![];
+    let mut bombs: Vec<&Bomb> = vec![];
+    let mut arcs: Vec<&Slider> = vec![];
+    for event in &beatmap.events {
+        match event {
+            Event::Note(note) => notes.push(note),
+            Event::Bomb(bomb) => bombs.push(bomb),
+            Event::Slider(slider) => arcs.push(slider),
+            _ => {}
+        }
+    }
+    notes.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+
+    let mut analysis = ParityAnalysis { swings: vec![], warnings: vec![] };
+    for color in [NoteColor::Red, NoteColor::Blue] {
+        let mut swings = swings_of(color, &notes, beatmap, timing);
+        for index in 0..swings.len() {
+            let Some(previous) = index.checked_sub(1).map(|x| swings[x].clone()) else {
+                swings[index].parity = if fits(swings[index].angle, Parity::Forehand) { Parity::Forehand } else { Parity::Backhand };
+                continue;
+            };
+            let swing = &mut swings[index];
+            let beat = swing.beat;
+            let bomb = || bombs.iter().any(|x| x.beat > previous.beat && x.beat < beat && forces_reset(x, &previous));
+            // Bombs only matter when the saber can't simply alternate, or for dots, which fit either parity
+            let is_dot = swing.angle.is_none();
+            swing.parity = previous.parity.flip();
+            if fits(swing.angle, swing.parity) && !(is_dot && bomb()) {
+                continue;
+            }
+            swing.parity = previous.parity;
+            if bomb() {
+                analysis.warnings.push(ParityWarning::BombReset { beat, color });
+                continue;
+            }
+            let gap = swing.start - previous.end;
+            let arc = arcs.iter().any(|x| x.color == color && x.head_beat == previous.beat && x.tail_beat == beat);
+            let same_direction = match (swing.angle, previous.angle) {
+                (Some(a), Some(b)) => normalize(a - b).abs() < SAME_DIRECTION,
+                _ => false,
+            };
+            analysis.warnings.push(if arc || gap >= MIN_RESET_TIME {
+                ParityWarning::Reset { beat, color }
+            } else if same_direction {
+                ParityWarning::DoubleDirectional { beat, color }
+            } else {
+                ParityWarning::ParityBreak { beat, color }
+            });
+        }
+        analysis.swings.extend(swings);
+    }
+    analysis.swings.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+    analysis.warnings.sort_by(|a, b| warning_beat(a).total_cmp(&warning_beat(b)));
+    analysis
+}
+
+fn warning_beat(warning: &ParityWarning) -> f64 {
+    match warning {
+        ParityWarning::ParityBreak { beat, .. } | ParityWarning::DoubleDirectional { beat, .. }
+        | ParityWarning::Reset { beat, .. } | ParityWarning::BombReset { beat, .. } => *beat,
+    }
+}
@@ -0,0 +1,107 @@
+//! Object counts and note density of a beatmap. Everything time related is in seconds, as given by the [Timing]
+
+use crate::types::common::NoteColor;
+use crate::types::primary::Event;
+use crate::{Beatmap, Timing};
+
+/// Counts and note times of a beatmap, see [Stats::new]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub notes: usize,
+    pub bombs: usize,
+    pub obstacles: usize,
+    pub sliders: usize,
+    pub burst_sliders: usize,
+    /// Seconds of every note and its color, sorted
+    note_times: Vec<(f64, NoteColor)>,
+}
+
+impl Stats {
+    pub fn new(beatmap: &Beatmap, timing: &Timing) -> Self {
+        let mut stats = Self { notes: 0, bombs: 0, obstacles: 0, sliders: 0, burst_sliders: 0, note_times: vec![] };
+        for event in &beatmap.events {
+            match event {
+                Event::Note(note) => stats.note_times.push((timing.beat_to_seconds(note.beat), note.color)),
+                Event::Bomb(_) => stats.bombs += 1,
+                Event::Obstacle(_) => stats.obstacles += 1,
+                Event::Slider(_) => stats.sliders += 1,
+                Event::BurstSlider(_) => stats.burst_sliders += 1,
+                _ => {}
+            }
+        }
+        stats.notes = stats.note_times.len();
+        stats.note_times.sort_by(|a, b| a.0.total_cmp(&b.0));
+        stats
+    }
+
+    fn times(&self, color: Option<NoteColor>) -> impl Iterator<Item = f64> + '_ {
+        self.note_times.iter().filter(move |(_, x)| color.map(|color| color == *x).unwrap_or(true)).map(|(time, _)| *time)
+    }
+
+    /// Notes of `color` (or all of them, if `None`)
+    pub fn note_count(&self, color: Option<NoteColor>) -> usize {
+        self.times(color).count()
+    }
+
+    /// Seconds from the first note to the last one
+    pub fn note_span(&self) -> f64 {
+        match (self.note_times.first(), self.note_times.last()) {
+            (Some(first), Some(last)) => last.0 - first.0,
+            _ => 0.0,
+        }
+    }
+
+    /// Notes per second over the span of the notes, see [Stats::note_span]
+    pub fn nps(&self) -> f64 {
+        let span = self.note_span();
+        if span > 0.0 { self.notes as f64 / span } else { 0.0 }
+    }
+
+    /// Notes per second over the whole song, `song_duration` seconds long
+    pub fn nps_over(&self, song_duration: f64) -> f64 {
+        if song_duration > 0.0 { self.notes as f64 / song_duration } else { 0.0 }
+    }
+
+    /// Highest notes per second within any `window` seconds long stretch, along with the second the stretch starts at
+    pub fn peak_nps(&self, window: f64) -> (f64, f64) {
+        let times: Vec<f64> = self.times(None).collect();
+        let mut peak = (0, 0.0);
+        let mut end = 0;
+        for (start, time) in times.iter().enumerate() {
+            end = end.max(start);
+            while end < times.len() && times[end] < time + window {
+                end += 1;
+            }
+            if end - start > peak.0 {
+                peak = (end - start, *time);
+            }
+        }
+        if window > 0.0 { (peak.0 as f64 / window, peak.1) } else { (0.0, 0.0) }
+    }
+
+    /// Notes per second of `color` (or all notes, if `None`) in consecutive sections `section_length` seconds long, starting at 0
+    pub fn density(&self, color: Option<NoteColor>, section_length: f64) -> Vec<f64> {
+        if section_length <= 0.0 {
+            return vec![];
+        }
+        let mut sections: Vec<f64> = vec![];
+        for time in self.times(color) {
+            let index = (time.max(0.0) / section_length) as usize;
+            if sections.len() <= index {
+                sections.resize(index + 1, 0.0);
+            }
+            sections[index] += 1.0;
+        }
+        sections.iter().map(|x| x / section_length).collect()
+    }
+
+    /// Notes per second of `color` (or all notes, if `None`) between each pair of consecutive `boundaries` (seconds, sorted),
+    /// e.g. for the sections of a song. Each section includes its start
+    pub fn section_density(&self, color: Option<NoteColor>, boundaries: &[f64]) -> Vec<f64> {
+        boundaries.windows(2).map(|section| {
+            let length = section[1] - section[0];
+            let count = self.times(color).filter(|x| *x >= section[0] && *x < section[1]).count();
+            if length > 0.0 { count as f64 / length } else { 0.0 }
+        }).collect()
+    }
+}
@@ -0,0 +1,100 @@
+use crate::types::primary::{AudioData, BPMEvent, Event};
+use crate::{Beatmap, BeatmapSetMeta};
+
+/// A span of the song with a constant BPM
+#[derive(Debug, Clone, PartialEq)]
+pub struct BpmRegion {
+    pub beat: f64,
+    pub seconds: f64,
+    pub bpm: f64,
+}
+
+/// Converts between beats and real time (in seconds), honoring BPM changes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timing {
+    pub song_offset: f64,
+    pub regions: Vec<BpmRegion>,
+}
+
+impl Timing {
+    pub fn new<'a>(bpm: f64, song_offset: f64, bpm_events: impl IntoIterator<Item = &'a BPMEvent>) -> Self {
+        let mut bpm_events: Vec<&BPMEvent> = bpm_events.into_iter().filter(|x| x.value > 0.0).collect();
+        bpm_events.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+
+        let mut regions = vec![BpmRegion { beat: 0.0, seconds: 0.0, bpm }];
+        for event in bpm_events {
+            let last = regions.last_mut().unwrap();
+            if event.beat <= last.beat {
+                last.bpm = event.value;
+                continue;
+            }
+            let seconds = last.seconds + (event.beat - last.beat) * 60.0 / last.bpm;
+            regions.push(BpmRegion { beat: event.beat, seconds, bpm: event.value });
+        }
+        Self { song_offset, regions }
+    }
+
+    pub fn from_beatmap(meta: &BeatmapSetMeta, beatmap: &Beatmap) -> Self {
+        let bpm_events = beatmap.events.iter().filter_map(|x| match x {
+            Event::BPM(event) => Some(event.as_ref()),
+            _ => None
+        });
+        Self::new(meta.bpm, meta.song_offset, bpm_events)
+    }
+
+    /// Timing from the sample regions of an audio data file. Falls back to a constant `bpm` if there are none
+    pub fn from_audio_data(audio_data: &AudioData, bpm: f64, song_offset: f64) -> Self {
+        let mut regions: Vec<BpmRegion> = audio_data.bpm_regions.iter()
+            .filter(|x| x.end_sample > x.start_sample && x.end_beat > x.start_beat)
+            .map(|x| {
+                let seconds = audio_data.sample_to_seconds(x.start_sample);
+                let duration = audio_data.sample_to_seconds(x.end_sample) - seconds;
+                BpmRegion { beat: x.start_beat, seconds, bpm: (x.end_beat - x.start_beat) / duration * 60.0 }
+            })
+            .collect();
+        regions.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+        if regions.is_empty() {
+            regions.push(BpmRegion { beat: 0.0, seconds: 0.0, bpm });
+        }
+        Self { song_offset, regions }
+    }
+
+    /// Uses the audio data of a v4 level when it has sample regions, as the game does, and the BPM events of the beatmap otherwise.
+    /// The game ignores `BPMInfo.dat` in older levels, so the audio data isn't used for those
+    pub fn from_level_data(meta: &BeatmapSetMeta, beatmap: &Beatmap, audio_data: Option<&AudioData>) -> Self {
+        match audio_data {
+            Some(audio_data) if meta.version.starts_with('4') && !audio_data.bpm_regions.is_empty() => Self::from_audio_data(audio_data, meta.bpm, meta.song_offset),
+            _ => Self::from_beatmap(meta, beatmap),
+        }
+    }
+
+    fn region_at_beat(&self, beat: f64) -> &BpmRegion {
+        let index = self.regions.partition_point(|x| x.beat <= beat);
+        &self.regions[index.saturating_sub(1)]
+    }
+
+    fn region_at_seconds(&self, seconds: f64) -> &BpmRegion {
+        let index = self.regions.partition_point(|x| x.seconds <= seconds);
+        &self.regions[index.saturating_sub(1)]
+    }
+
+    pub fn bpm_at(&self, beat: f64) -> f64 {
+        self.region_at_beat(beat).bpm
+    }
+
+    pub fn beat_to_seconds(&self, beat: f64) -> f64 {
+        let region = self.region_at_beat(beat);
+        region.seconds + (beat - region.beat) * 60.0 / region.bpm + self.song_offset
+    }
+
+    pub fn seconds_to_beat(&self, seconds: f64) -> f64 {
+        let seconds = seconds - self.song_offset;
+        let region = self.region_at_seconds(seconds);
+        region.beat + (seconds - region.seconds) * region.bpm / 60.0
+    }
+
+    /// Length of the given duration in beats starting at `beat`, in seconds
+    pub fn duration_to_seconds(&self, beat: f64, duration: f64) -> f64 {
+        self.beat_to_seconds(beat + duration) - self.beat_to_seconds(beat)
+    }
+}
@@ -2,4 +2,8 @@ pub mod lightning;
 pub mod schema;
 pub mod common;
 pub mod converters;
+pub mod converters_v4;
 pub mod primary;
+pub mod custom_data;
+pub mod heck;
+pub mod songcore;
@@ -1,17 +1,17 @@
 use std::{fs::File, path::Path};
-use std::io::Read;
+use std::io::{Read, Write};
 use serde::{Serialize, Deserialize};
 use serde_repr::{Serialize_repr, Deserialize_repr};
 use crate::error::Result;
 
-#[derive(Serialize_repr, Deserialize_repr, Debug, PartialEq)]
+#[derive(Serialize_repr, Deserialize_repr, Debug, Clone, Copy, PartialEq)]
 #[repr(i8)]
 pub enum NoteColor {
     Red = 0,
     Blue = 1
 }
 
-#[derive(Serialize_repr, Deserialize_repr, Debug, PartialEq)]
+#[derive(Serialize_repr, Deserialize_repr, Debug, Clone, Copy, PartialEq)]
 #[repr(i8)]
 pub enum Direction {
     Up = 0,
@@ -25,7 +25,7 @@ pub enum Direction {
     Any = 8
 }
 
-#[derive(Serialize_repr, Deserialize_repr, Debug, PartialEq)]
+#[derive(Serialize_repr, Deserialize_repr, Debug, Clone, Copy, PartialEq)]
 #[repr(i8)]
 pub enum SliderMidAnchorMode {
     Straight = 0,
@@ -33,14 +33,14 @@ pub enum SliderMidAnchorMode {
     CounterClockwise = 2,
 }
 
-#[derive(Serialize_repr, Deserialize_repr, Debug, PartialEq)]
+#[derive(Serialize_repr, Deserialize_repr, Debug, Clone, Copy, PartialEq)]
 #[repr(i8)]
 pub enum RotationBehaviour {
     Transition = 0,
     Extend = 1,
 }
 
-#[derive(Serialize_repr, Deserialize_repr, Debug, PartialEq)]
+#[derive(Serialize_repr, Deserialize_repr, Debug, Clone, Copy, PartialEq)]
 #[repr(i8)]
 pub enum RotationDirection {
     Automatic = 0,
@@ -48,14 +48,14 @@ pub enum RotationDirection {
     CounterClockwise = 2,
 }
 
-#[derive(Serialize_repr, Deserialize_repr, Debug, PartialEq)]
+#[derive(Serialize_repr, Deserialize_repr, Debug, Clone, Copy, PartialEq)]
 #[repr(i8)]
 pub enum DistributionKind {
     Wave = 1,
     Step = 2,
 }
 
-#[derive(Serialize_repr, Deserialize_repr, Debug, PartialEq)]
+#[derive(Serialize_repr, Deserialize_repr, Debug, Clone, Copy, PartialEq)]
 #[repr(i8)]
 pub enum Easing {
     None = -1,
@@ -65,7 +65,7 @@ pub enum Easing {
     EaseInOutQuad = 3,
 }
 
-#[derive(Serialize_repr, Deserialize_repr, Debug, PartialEq)]
+#[derive(Serialize_repr, Deserialize_repr, Debug, Clone, Copy, PartialEq)]
 #[repr(i8)]
 pub enum Axis {
     X = 0,
@@ -73,7 +73,7 @@ pub enum Axis {
     Z = 2
 }
 
-#[derive(Serialize_repr, Deserialize_repr, Debug, PartialEq)]
+#[derive(Serialize_repr, Deserialize_repr, Debug, Clone, Copy, PartialEq)]
 #[repr(i8)]
 pub enum TransitionKind {
     Instant = 0,
@@ -81,7 +81,7 @@ pub enum TransitionKind {
     Extend = 2,
 }
 
-#[derive(Serialize_repr, Deserialize_repr, Debug, PartialEq)]
+#[derive(Serialize_repr, Deserialize_repr, Debug, Clone, Copy, PartialEq)]
 #[repr(i8)]
 pub enum LightColor {
     Red = 0,
@@ -89,7 +89,7 @@ pub enum LightColor {
     White = 2,
 }
 
-#[derive(Serialize_repr, Deserialize_repr, Debug, PartialEq)]
+#[derive(Serialize_repr, Deserialize_repr, Debug, Clone, Copy, PartialEq)]
 #[repr(i8)]
 pub enum BoxFilterOrdering {
     Standard1 = 0,
@@ -98,7 +98,7 @@ pub enum BoxFilterOrdering {
     RandomStartingIndex = 3,
 }
 
-#[derive(Serialize_repr, Deserialize_repr, Debug, PartialEq)]
+#[derive(Serialize_repr, Deserialize_repr, Debug, Clone, Copy, PartialEq)]
 #[repr(i8)]
 pub enum LimitKind {
     Sections = 0,
@@ -107,14 +107,14 @@ pub enum LimitKind {
     SectionsDurationBrightness = 3,
 }
 
-#[derive(Serialize_repr, Deserialize_repr, Debug, PartialEq)]
+#[derive(Serialize_repr, Deserialize_repr, Debug, Clone, Copy, PartialEq)]
 #[repr(i8)]
 pub enum BoxFilterKind {
     Sections = 1,
     StepAndOffset = 2
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Difficulty {
     Easy,
     Normal,
@@ -135,9 +135,166 @@ impl Difficulty {
     }
 }
 
+/// The `type` (`et` in v3) of a basic event. Values without a variant fail to convert and should be kept as plain numbers
+#[derive(Serialize_repr, Deserialize_repr, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(i32)]
+pub enum BasicEventType {
+    BackLasers = 0,
+    RingLights = 1,
+    LeftLasers = 2,
+    RightLasers = 3,
+    CenterLights = 4,
+    ColorBoost = 5,
+    ExtraLeftLights = 6,
+    ExtraRightLights = 7,
+    RingSpin = 8,
+    RingZoom = 9,
+    ExtraLeftLasers = 10,
+    ExtraRightLasers = 11,
+    LeftLaserSpeed = 12,
+    RightLaserSpeed = 13,
+    EarlyRotation = 14,
+    LateRotation = 15,
+    UtilityEvent0 = 16,
+    UtilityEvent1 = 17,
+    UtilityEvent2 = 18,
+    UtilityEvent3 = 19,
+    SpecialEvent0 = 40,
+    SpecialEvent1 = 41,
+    SpecialEvent2 = 42,
+    SpecialEvent3 = 43,
+    BpmChange = 100,
+}
+
+impl BasicEventType {
+    /// Types whose value is a [LightValue]
+    pub fn is_light(&self) -> bool {
+        use BasicEventType::*;
+        matches!(self, BackLasers | RingLights | LeftLasers | RightLasers | CenterLights | ExtraLeftLights | ExtraRightLights | ExtraLeftLasers | ExtraRightLasers)
+    }
+}
+
+impl TryFrom<i32> for BasicEventType {
+    type Error = i32;
+
+    fn try_from(value: i32) -> std::result::Result<Self, i32> {
+        use BasicEventType::*;
+        Ok(match value {
+            0 => BackLasers,
+            1 => RingLights,
+            2 => LeftLasers,
+            3 => RightLasers,
+            4 => CenterLights,
+            5 => ColorBoost,
+            6 => ExtraLeftLights,
+            7 => ExtraRightLights,
+            8 => RingSpin,
+            9 => RingZoom,
+            10 => ExtraLeftLasers,
+            11 => ExtraRightLasers,
+            12 => LeftLaserSpeed,
+            13 => RightLaserSpeed,
+            14 => EarlyRotation,
+            15 => LateRotation,
+            16 => UtilityEvent0,
+            17 => UtilityEvent1,
+            18 => UtilityEvent2,
+            19 => UtilityEvent3,
+            40 => SpecialEvent0,
+            41 => SpecialEvent1,
+            42 => SpecialEvent2,
+            43 => SpecialEvent3,
+            100 => BpmChange,
+            _ => return Err(value),
+        })
+    }
+}
+
+impl From<BasicEventType> for i32 {
+    fn from(value: BasicEventType) -> Self {
+        value as i32
+    }
+}
+
+/// The value of a basic light event. Values without a variant fail to convert and should be kept as plain numbers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightValue {
+    Off,
+    On(LightColor),
+    Flash(LightColor),
+    Fade(LightColor),
+    /// Blends from the previous event into this one
+    Transition(LightColor),
+}
+
+impl LightValue {
+    pub fn color(&self) -> Option<LightColor> {
+        match self {
+            LightValue::Off => None,
+            LightValue::On(color) | LightValue::Flash(color) | LightValue::Fade(color) | LightValue::Transition(color) => Some(*color),
+        }
+    }
+}
+
+impl TryFrom<i32> for LightValue {
+    type Error = i32;
+
+    fn try_from(value: i32) -> std::result::Result<Self, i32> {
+        let color = match value {
+            0 => return Ok(LightValue::Off),
+            1..=4 => LightColor::Blue,
+            5..=8 => LightColor::Red,
+            9..=12 => LightColor::White,
+            _ => return Err(value),
+        };
+        Ok(match (value - 1) % 4 {
+            0 => LightValue::On(color),
+            1 => LightValue::Flash(color),
+            2 => LightValue::Fade(color),
+            _ => LightValue::Transition(color),
+        })
+    }
+}
+
+impl From<LightValue> for i32 {
+    fn from(value: LightValue) -> Self {
+        let offset = match value.color() {
+            None => return 0,
+            Some(LightColor::Blue) => 0,
+            Some(LightColor::Red) => 4,
+            Some(LightColor::White) => 8,
+        };
+        offset + match value {
+            LightValue::Off => 0,
+            LightValue::On(_) => 1,
+            LightValue::Flash(_) => 2,
+            LightValue::Fade(_) => 3,
+            LightValue::Transition(_) => 4,
+        }
+    }
+}
+
+/// Rotation amounts (in degrees) encoded by the values 0-7 of v2 rotation events
+pub const LEGACY_ROTATION_VALUES: [f64; 8] = [-60.0, -45.0, -30.0, -15.0, 15.0, 30.0, 45.0, 60.0];
+
+/// Decodes the value of a v2 rotation event into degrees. Values 1000-1720 are the Mapping Extensions encoding of -360..360
+pub fn decode_legacy_rotation(value: i32) -> Option<f64> {
+    match value {
+        0..=7 => Some(LEGACY_ROTATION_VALUES[value as usize]),
+        1000..=1720 => Some((value - 1360) as f64),
+        _ => None
+    }
+}
+
 pub(crate) fn read_string_from_file(path: impl AsRef<Path>) -> Result<String> {
     let mut file = File::open(path)?;
     let mut data = String::new();
     file.read_to_string(&mut data)?;
     Ok(data)
 }
+
+pub(crate) fn write_string_to_file(path: impl AsRef<Path>, data: &str) -> Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(data.as_bytes())?;
+    Ok(())
+}
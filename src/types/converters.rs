@@ -3,18 +3,29 @@ use std::path::Path;
 use crate::types::primary::{BasicEvent, BeatmapMeta, Bomb, BPMEvent, BurstSlider, ColorBoost, DifficultySet, Event, BeatmapSetMeta, Note, Obstacle, Rotation, Slider};
 use crate::types::{primary, schema};
 use crate::Beatmap;
-use crate::types::common::{BoxFilterKind, NoteColor, read_string_from_file};
-use crate::error::Result;
+use crate::types::common::{Axis, BasicEventType, BoxFilterKind, Direction, NoteColor, LEGACY_ROTATION_VALUES, decode_legacy_rotation, read_string_from_file, write_string_to_file};
+use crate::error::{ConversionWarning, Result};
+use crate::types::custom_data::{custom_data_from_v2, custom_data_to_v2};
+use crate::types::songcore::songcore_keys_to_version;
 use crate::types::lightning::{BoxFilter, BoxFilterSettings, LightColorEvent, LightEventBox, LightEventLane, LightEvents, LightRotationEvent, LightTranslationEvent};
 
 impl BeatmapSetMeta {
     pub fn read_from_str(data: &str) -> Result<Self> {
-        Ok(schema::Info::read_from_str(data)?.into())
+        Ok(schema::InfoFile::read_from_str(data)?.into())
     }
 
     pub fn read_from_file(path: impl AsRef<Path>) -> Result<Self> {
         Self::read_from_str(&read_string_from_file(path)?)
     }
+
+    /// Serializes the info in the 2.0.0 format
+    pub fn write_to_string(&self) -> Result<String> {
+        schema::Info::from(self).write_to_string()
+    }
+
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        write_string_to_file(path, &self.write_to_string()?)
+    }
 }
 
 impl From<schema::Info> for BeatmapSetMeta {
@@ -35,8 +46,23 @@ impl From<schema::Info> for BeatmapSetMeta {
             environment_name: info._environmentName,
             all_directions_environment_name: info._allDirectionsEnvironmentName,
             song_offset: info._songTimeOffset,
+            song_duration: None,
+            audio_data_filename: None,
+            lufs: None,
+            song_preview_filename: None,
+            color_schemes: vec![],
             custom_data: info._customData,
-            difficulty_sets: info._difficultyBeatmapSets.into_iter().map(DifficultySet::from).collect()
+            difficulty_sets: info._difficultyBeatmapSets.into_iter().map(DifficultySet::from).collect(),
+            extras: info.extras,
+        }
+    }
+}
+
+impl From<schema::InfoFile> for BeatmapSetMeta {
+    fn from(file: schema::InfoFile) -> Self {
+        match file {
+            schema::InfoFile::Old(info) => (*info).into(),
+            schema::InfoFile::V4(info) => (*info).into(),
         }
     }
 }
@@ -45,7 +71,8 @@ impl From<schema::DifficultyBeatmapSet> for DifficultySet {
     fn from(set: schema::DifficultyBeatmapSet) -> Self {
         Self {
             game_mode: set._beatmapCharacteristicName,
-            beatmaps: set._difficultyBeatmaps.into_iter().map(BeatmapMeta::from).collect()
+            beatmaps: set._difficultyBeatmaps.into_iter().map(BeatmapMeta::from).collect(),
+            extras: set.extras,
         }
     }
 }
@@ -58,332 +85,454 @@ impl From<schema::DifficultyBeatmap> for BeatmapMeta {
             filename: beatmap._beatmapFilename,
             note_jump_speed: beatmap._noteJumpMovementSpeed,
             note_jump_start_beat_offset: beatmap._noteJumpStartBeatOffset,
-            custom_data: beatmap._customData
+            mappers: vec![],
+            lighters: vec![],
+            environment_name: None,
+            color_scheme: None,
+            lightshow_filename: None,
+            custom_data: beatmap._customData,
+            extras: beatmap.extras,
         }
     }
 }
 
-impl Into<Option<Event>> for schema::OldNote {
-    fn into(self) -> Option<Event> {
+impl From<&BeatmapSetMeta> for schema::Info {
+    fn from(meta: &BeatmapSetMeta) -> Self {
+        Self {
+            _version: if meta.version.starts_with('2') { meta.version.clone() } else { "2.0.0".to_string() },
+            _songName: meta.song_name.clone(),
+            _songSubName: meta.song_subname.clone(),
+            _songAuthorName: meta.song_author.clone(),
+            _levelAuthorName: meta.map_author.clone(),
+            _beatsPerMinute: meta.bpm,
+            _shuffle: meta.shuffle,
+            _shufflePeriod: meta.shuffle_period,
+            _previewStartTime: meta.preview_start,
+            _previewDuration: meta.preview_duration,
+            _songFilename: meta.song_filename.clone(),
+            _coverImageFilename: meta.cover_image_filename.clone(),
+            _environmentName: meta.environment_name.clone(),
+            _allDirectionsEnvironmentName: meta.all_directions_environment_name.clone(),
+            _songTimeOffset: meta.song_offset,
+            _customData: songcore_keys_to_version(&meta.custom_data, false),
+            _difficultyBeatmapSets: meta.difficulty_sets.iter().map(schema::DifficultyBeatmapSet::from).collect(),
+            extras: meta.extras.clone(),
+        }
+    }
+}
+
+impl From<&DifficultySet> for schema::DifficultyBeatmapSet {
+    fn from(set: &DifficultySet) -> Self {
+        Self {
+            _beatmapCharacteristicName: set.game_mode.clone(),
+            _difficultyBeatmaps: set.beatmaps.iter().map(schema::DifficultyBeatmap::from).collect(),
+            extras: set.extras.clone(),
+        }
+    }
+}
+
+impl From<&BeatmapMeta> for schema::DifficultyBeatmap {
+    fn from(beatmap: &BeatmapMeta) -> Self {
+        Self {
+            _difficulty: beatmap.difficulty,
+            _difficultyRank: beatmap.rank,
+            _beatmapFilename: beatmap.filename.clone(),
+            _noteJumpMovementSpeed: beatmap.note_jump_speed,
+            _noteJumpStartBeatOffset: beatmap.note_jump_start_beat_offset,
+            _customData: songcore_keys_to_version(&beatmap.custom_data, false),
+            extras: beatmap.extras.clone(),
+        }
+    }
+}
+
+impl From<schema::OldNote> for Option<Event> {
+    fn from(note: schema::OldNote) -> Self {
         use schema::OldNoteKind::*;
-        match self._type {
+        match note._type {
             Red | Blue => Some(Event::Note(Box::new(Note {
-                beat: self._time,
-                x: self._lineIndex,
-                y: self._lineLayer,
-                color: if self._type == Red { NoteColor::Red } else { NoteColor::Blue },
-                direction: self._cutDirection,
+                beat: note._time,
+                x: note._lineIndex,
+                y: note._lineLayer,
+                color: if note._type == Red { NoteColor::Red } else { NoteColor::Blue },
+                direction: note._cutDirection,
                 angle_offset: 0.0,
+                custom_data: note._customData,
+                extras: note.extras,
             }))),
             Bomb => Some(Event::Bomb(Box::new(primary::Bomb {
-                beat: self._time,
-                x: self._lineIndex,
-                y: self._lineLayer,
+                beat: note._time,
+                x: note._lineIndex,
+                y: note._lineLayer,
+                custom_data: note._customData,
+                extras: note.extras,
             }))),
             Unused => None
         }
     }
 }
 
-impl Into<Event> for schema::OldSlider {
-    fn into(self) -> Event {
+impl From<schema::OldSlider> for Event {
+    fn from(slider: schema::OldSlider) -> Self {
         Event::Slider(Box::new(Slider {
-            head_beat: self._headTime,
-            color: self._colorType,
-            head_x: self._headLineIndex,
-            head_y: self._headLineLayer,
-            head_direction: self._headCutDirection,
-            head_bulge: self._headControlPointLengthMultiplier,
-            tail_beat: self._tailTime,
-            tail_x: self._tailLineIndex,
-            tail_y: self._tailLineLayer,
-            tail_direction: self._tailCutDirection,
-            tail_bulge: self._tailControlPointLengthMultiplier,
-            special_curving: self._sliderMidAnchorMode,
+            head_beat: slider._headTime,
+            color: slider._colorType,
+            head_x: slider._headLineIndex,
+            head_y: slider._headLineLayer,
+            head_direction: slider._headCutDirection,
+            head_bulge: slider._headControlPointLengthMultiplier,
+            tail_beat: slider._tailTime,
+            tail_x: slider._tailLineIndex,
+            tail_y: slider._tailLineLayer,
+            tail_direction: slider._tailCutDirection,
+            tail_bulge: slider._tailControlPointLengthMultiplier,
+            special_curving: slider._sliderMidAnchorMode,
+            custom_data: slider._customData,
+            extras: slider.extras,
         }))
     }
 }
 
-impl Into<Event> for schema::OldObstacle {
-    fn into(self) -> Event {
+impl From<schema::OldObstacle> for Event {
+    fn from(obstacle: schema::OldObstacle) -> Self {
         Event::Obstacle(Box::new(Obstacle {
-            beat: self._time,
-            x: self._lineIndex,
-            y: if self._type == schema::OldObstacleKind::Full { 0 } else { 2 },
-            duration: self._duration,
-            width: self._width as f64,
-            height: if self._type == schema::OldObstacleKind::Full { 5.0 } else { 2.0 },
+            beat: obstacle._time,
+            x: obstacle._lineIndex,
+            y: if obstacle._type == schema::OldObstacleKind::Full { 0 } else { 2 },
+            duration: obstacle._duration,
+            width: obstacle._width,
+            height: if obstacle._type == schema::OldObstacleKind::Full { 5.0 } else { 2.0 },
+            custom_data: obstacle._customData,
+            extras: obstacle.extras,
         }))
     }
 }
 
-impl Into<Event> for schema::OldEvent {
-    fn into(self) -> Event {
+impl From<schema::OldEvent> for Event {
+    fn from(event: schema::OldEvent) -> Self {
+        match BasicEventType::try_from(event._type) {
+            Ok(BasicEventType::ColorBoost) => return Event::ColorBoost(Box::new(ColorBoost {
+                beat: event._time,
+                enable: event._value == 1,
+                extras: event.extras,
+            })),
+            Ok(BasicEventType::EarlyRotation | BasicEventType::LateRotation) => if let Some(value) = decode_legacy_rotation(event._value) {
+                return Event::Rotation(Box::new(Rotation {
+                    beat: event._time,
+                    is_late: event._type == i32::from(BasicEventType::LateRotation),
+                    value,
+                    extras: event.extras,
+                }))
+            },
+            Ok(BasicEventType::BpmChange) => return Event::BPM(Box::new(BPMEvent {
+                beat: event._time,
+                value: event._floatValue.unwrap_or(event._value as f64),
+                extras: event.extras,
+            })),
+            _ => {}
+        }
         Event::BasicEvent(Box::new(BasicEvent {
-            beat: self._time,
-            kind: self._type,
-            value: self._value,
-            float_value: self._floatValue,
-            custom_data: self._customData,
+            beat: event._time,
+            kind: event._type,
+            value: event._value,
+            float_value: event._floatValue,
+            custom_data: event._customData,
+            extras: event.extras,
         }))
     }
 }
 
-impl Into<Event> for schema::BpmEvent {
-    fn into(self) -> Event {
+impl From<schema::BpmEvent> for Event {
+    fn from(event: schema::BpmEvent) -> Self {
         Event::BPM(Box::new(BPMEvent {
-            beat: self.b,
-            value: self.m,
+            beat: event.b,
+            value: event.m,
+            extras: event.extras,
         }))
     }
 }
 
-impl Into<Event> for schema::RotationEvent {
-    fn into(self) -> Event {
+impl From<schema::RotationEvent> for Event {
+    fn from(event: schema::RotationEvent) -> Self {
         Event::Rotation(Box::new(Rotation {
-            beat: self.b,
-            is_late: self.e,
-            value: self.r,
+            beat: event.b,
+            is_late: event.e,
+            value: event.r,
+            extras: event.extras,
         }))
     }
 }
 
-impl Into<Event> for schema::ColorNote {
-    fn into(self) -> Event {
+impl From<schema::ColorNote> for Event {
+    fn from(note: schema::ColorNote) -> Self {
         Event::Note(Box::new(Note {
-            beat: self.b,
-            x: self.x,
-            y: self.y,
-            color: self.c,
-            direction: self.d,
-            angle_offset: self.a as f64,
+            beat: note.b,
+            x: note.x,
+            y: note.y,
+            color: note.c,
+            direction: note.d,
+            angle_offset: note.a as f64,
+            custom_data: note.customData,
+            extras: note.extras,
         }))
     }
 }
 
-impl Into<Event> for schema::BombNote {
-    fn into(self) -> Event {
+impl From<schema::BombNote> for Event {
+    fn from(bomb: schema::BombNote) -> Self {
         Event::Bomb(Box::new(Bomb {
-            beat: self.b,
-            x: self.x,
-            y: self.y,
+            beat: bomb.b,
+            x: bomb.x,
+            y: bomb.y,
+            custom_data: bomb.customData,
+            extras: bomb.extras,
         }))
     }
 }
 
-impl Into<Event> for schema::Obstacle {
-    fn into(self) -> Event {
+impl From<schema::Obstacle> for Event {
+    fn from(obstacle: schema::Obstacle) -> Self {
         Event::Obstacle(Box::new(Obstacle {
-            beat: self.b,
-            x: self.x,
-            y: self.y,
-            duration: self.d,
-            width: self.w,
-            height: self.h,
+            beat: obstacle.b,
+            x: obstacle.x,
+            y: obstacle.y,
+            duration: obstacle.d,
+            width: obstacle.w,
+            height: obstacle.h,
+            custom_data: obstacle.customData,
+            extras: obstacle.extras,
         }))
     }
 }
 
-impl Into<Event> for schema::Slider {
-    fn into(self) -> Event {
+impl From<schema::Slider> for Event {
+    fn from(slider: schema::Slider) -> Self {
         Event::Slider(Box::new(Slider {
-            head_beat: self.b,
-            color: self.c,
-            head_x: self.x,
-            head_y: self.y,
-            head_direction: self.d,
-            head_bulge: self.mu,
-            tail_beat: self.tb,
-            tail_x: self.tx,
-            tail_y: self.ty,
-            tail_direction: self.tc,
-            tail_bulge: self.tmu,
-            special_curving: self.m,
+            head_beat: slider.b,
+            color: slider.c,
+            head_x: slider.x,
+            head_y: slider.y,
+            head_direction: slider.d,
+            head_bulge: slider.mu,
+            tail_beat: slider.tb,
+            tail_x: slider.tx,
+            tail_y: slider.ty,
+            tail_direction: slider.tc,
+            tail_bulge: slider.tmu,
+            special_curving: slider.m,
+            custom_data: slider.customData,
+            extras: slider.extras,
         }))
     }
 }
 
-impl Into<Event> for schema::BurstSlider {
-    fn into(self) -> Event {
+impl From<schema::BurstSlider> for Event {
+    fn from(slider: schema::BurstSlider) -> Self {
         Event::BurstSlider(Box::new(BurstSlider {
-            head_beat: self.b,
-            color: self.c,
-            head_x: self.x,
-            head_y: self.y,
-            head_direction: self.d,
-            tail_beat: self.tb,
-            tail_x: self.tx,
-            tail_y: self.ty,
-            segment_count: self.sc,
-            squish: self.s,
+            head_beat: slider.b,
+            color: slider.c,
+            head_x: slider.x,
+            head_y: slider.y,
+            head_direction: slider.d,
+            tail_beat: slider.tb,
+            tail_x: slider.tx,
+            tail_y: slider.ty,
+            segment_count: slider.sc,
+            squish: slider.s,
+            custom_data: slider.customData,
+            extras: slider.extras,
         }))
     }
 }
 
-impl Into<Event> for schema::BasicBeatmapEvent {
-    fn into(self) -> Event {
+impl From<schema::BasicBeatmapEvent> for Event {
+    fn from(event: schema::BasicBeatmapEvent) -> Self {
         Event::BasicEvent(Box::new(BasicEvent {
-            beat: self.b,
-            kind: self.et,
-            value: self.i,
-            float_value: self.f,
-            custom_data: Default::default(),
+            beat: event.b,
+            kind: event.et,
+            value: event.i,
+            float_value: event.f,
+            custom_data: event.customData,
+            extras: event.extras,
         }))
     }
 }
 
-impl Into<Event> for schema::ColorBoostBeatmapEvent {
-    fn into(self) -> Event {
+impl From<schema::ColorBoostBeatmapEvent> for Event {
+    fn from(event: schema::ColorBoostBeatmapEvent) -> Self {
         Event::ColorBoost(Box::new(ColorBoost {
-            beat: self.b,
-            enable: self.o,
+            beat: event.b,
+            enable: event.o,
+            extras: event.extras,
         }))
     }
 }
 
-impl Into<Event> for schema::LightColorEventBoxGroup {
-    fn into(self) -> Event {
+impl From<schema::LightColorEventBoxGroup> for Event {
+    fn from(group: schema::LightColorEventBoxGroup) -> Self {
         Event::LightEventBox(Box::new(LightEventBox {
-            beat: self.b,
-            group: self.g,
-            lanes: self.e.into_iter().map(|x| Into::<LightEventLane>::into(x)).collect(),
+            beat: group.b,
+            group: group.g,
+            lanes: group.e.into_iter().map(LightEventLane::from).collect(),
+            extras: group.extras,
         }))
     }
 }
 
-impl Into<Event> for schema::LightRotationEventBoxGroup {
-    fn into(self) -> Event {
+impl From<schema::LightRotationEventBoxGroup> for Event {
+    fn from(group: schema::LightRotationEventBoxGroup) -> Self {
         Event::LightEventBox(Box::new(LightEventBox {
-            beat: self.b,
-            group: self.g,
-            lanes: self.e.into_iter().map(|x| Into::<LightEventLane>::into(x)).collect(),
+            beat: group.b,
+            group: group.g,
+            lanes: group.e.into_iter().map(LightEventLane::from).collect(),
+            extras: group.extras,
         }))
     }
 }
 
-impl Into<Event> for schema::LightTranslationEventBoxGroup {
-    fn into(self) -> Event {
+impl From<schema::LightTranslationEventBoxGroup> for Event {
+    fn from(group: schema::LightTranslationEventBoxGroup) -> Self {
         Event::LightEventBox(Box::new(LightEventBox {
-            beat: self.b,
-            group: self.g,
-            lanes: self.e.into_iter().map(|x| Into::<LightEventLane>::into(x)).collect(),
+            beat: group.b,
+            group: group.g,
+            lanes: group.e.into_iter().map(LightEventLane::from).collect(),
+            extras: group.extras,
         }))
     }
 }
 
-impl Into<LightEventLane> for schema::LightColorEventBoxGroupLane {
-    fn into(self) -> LightEventLane {
+impl From<schema::LightColorEventBoxGroupLane> for LightEventLane {
+    fn from(lane: schema::LightColorEventBoxGroupLane) -> Self {
         LightEventLane {
-            filter: self.f.into(),
-            beat_dist: self.w,
-            beat_dist_kind: self.d,
-            dist: self.r,
-            dist_kind: self.t,
-            dist_affects_first_event: self.b,
-            dist_easing: self.i,
+            filter: lane.f.into(),
+            beat_dist: lane.w,
+            beat_dist_kind: lane.d,
+            dist: lane.r,
+            dist_kind: lane.t,
+            dist_affects_first_event: lane.b,
+            dist_easing: lane.i,
             axis: None,
             reverse: None,
-            events: LightEvents::Color(self.e.into_iter().map(|x| Into::<LightColorEvent>::into(x)).collect())
+            events: LightEvents::Color(lane.e.into_iter().map(LightColorEvent::from).collect()),
+            extras: lane.extras,
         }
     }
 }
 
-impl Into<LightEventLane> for schema::LightRotationEventBoxGroupLane {
-    fn into(self) -> LightEventLane {
+impl From<schema::LightRotationEventBoxGroupLane> for LightEventLane {
+    fn from(lane: schema::LightRotationEventBoxGroupLane) -> Self {
         LightEventLane {
-            filter: self.f.into(),
-            beat_dist: self.w,
-            beat_dist_kind: self.d,
-            dist: self.s,
-            dist_kind: self.t,
-            dist_affects_first_event: self.b,
-            dist_easing: self.i,
-            axis: Some(self.a),
-            reverse: Some(self.r),
-            events: LightEvents::Rotation(self.e.into_iter().map(|x| Into::<LightRotationEvent>::into(x)).collect())
+            filter: lane.f.into(),
+            beat_dist: lane.w,
+            beat_dist_kind: lane.d,
+            dist: lane.s,
+            dist_kind: lane.t,
+            dist_affects_first_event: lane.b,
+            dist_easing: lane.i,
+            axis: Some(lane.a),
+            reverse: Some(lane.r),
+            events: LightEvents::Rotation(lane.e.into_iter().map(LightRotationEvent::from).collect()),
+            extras: lane.extras,
         }
     }
 }
 
-impl Into<LightEventLane> for schema::LightTranslationEventBoxGroupLane {
-    fn into(self) -> LightEventLane {
+impl From<schema::LightTranslationEventBoxGroupLane> for LightEventLane {
+    fn from(lane: schema::LightTranslationEventBoxGroupLane) -> Self {
         LightEventLane {
-            filter: self.f.into(),
-            beat_dist: self.w,
-            beat_dist_kind: self.d,
-            dist: self.s,
-            dist_kind: self.t,
-            dist_affects_first_event: self.b,
-            dist_easing: self.i,
-            axis: Some(self.a),
-            reverse: Some(self.r),
-            events: LightEvents::Translation(self.l.into_iter().map(|x| Into::<LightTranslationEvent>::into(x)).collect())
+            filter: lane.f.into(),
+            beat_dist: lane.w,
+            beat_dist_kind: lane.d,
+            dist: lane.s,
+            dist_kind: lane.t,
+            dist_affects_first_event: lane.b,
+            dist_easing: lane.i,
+            axis: Some(lane.a),
+            reverse: Some(lane.r),
+            events: LightEvents::Translation(lane.l.into_iter().map(LightTranslationEvent::from).collect()),
+            extras: lane.extras,
         }
     }
 }
 
-impl Into<LightColorEvent> for schema::LightColorEventData {
-    fn into(self) -> LightColorEvent {
+impl From<schema::LightColorEventData> for LightColorEvent {
+    fn from(event: schema::LightColorEventData) -> Self {
         LightColorEvent {
-            relative_beat: self.b,
-            transition_kind: self.i,
-            color: self.c,
-            brightness: self.s,
-            frequency: self.f,
+            relative_beat: event.b,
+            transition_kind: event.i,
+            color: event.c,
+            brightness: event.s,
+            frequency: event.f,
+            extras: event.extras,
         }
     }
 }
 
-impl Into<LightRotationEvent> for schema::LightRotationEventData {
-    fn into(self) -> LightRotationEvent {
+impl From<schema::LightRotationEventData> for LightRotationEvent {
+    fn from(event: schema::LightRotationEventData) -> Self {
         LightRotationEvent {
-            relative_beat: self.b,
-            behaviour: self.p,
-            easing: self.e,
-            loops: self.l,
-            amount: self.r,
-            direction: self.o,
+            relative_beat: event.b,
+            behaviour: event.p,
+            easing: event.e,
+            loops: event.l,
+            amount: event.r,
+            direction: event.o,
+            extras: event.extras,
         }
     }
 }
 
-impl Into<LightTranslationEvent> for schema::LightTranslationEventData {
-    fn into(self) -> LightTranslationEvent {
+impl From<schema::LightTranslationEventData> for LightTranslationEvent {
+    fn from(event: schema::LightTranslationEventData) -> Self {
         LightTranslationEvent {
-            relative_beat: self.b,
-            rotation_behaviour: self.p,
-            easing: self.e,
-            amount: self.t,
+            relative_beat: event.b,
+            rotation_behaviour: event.p,
+            easing: event.e,
+            amount: event.t,
+            extras: event.extras,
         }
     }
 }
 
-impl Into<BoxFilter> for schema::FilterObject {
-    fn into(self) -> BoxFilter {
+impl From<schema::FilterObject> for BoxFilter {
+    fn from(filter: schema::FilterObject) -> Self {
         BoxFilter {
-            chunks: self.c,
-            settings: match self.f {
+            chunks: filter.c,
+            settings: match filter.f {
                 BoxFilterKind::Sections =>
                     BoxFilterSettings::Sections {
-                        count: self.p,
-                        index: self.t,
+                        count: filter.p,
+                        index: filter.t,
                     },
                 BoxFilterKind::StepAndOffset =>
                     BoxFilterSettings::StepAndOffset {
-                        start: self.p,
-                        skip: self.t,
+                        start: filter.p,
+                        skip: filter.t,
                     }
             },
-            reverse: self.r,
-            ordering: self.n,
-            random_seed: self.s,
-            limit: self.l,
-            limit_kind: self.d,
+            reverse: filter.r,
+            ordering: filter.n,
+            random_seed: filter.s,
+            limit: filter.l,
+            limit_kind: filter.d,
+            extras: filter.extras,
         }
     }
 }
 
 
+fn object_custom_data_v2(beat: f64, data: &HashMap<String, serde_json::Value>, warnings: &mut Vec<ConversionWarning>) -> HashMap<String, serde_json::Value> {
+    let (data, kept) = custom_data_to_v2(data);
+    warnings.extend(kept.into_iter().map(|key| ConversionWarning::CustomDataKept { beat, key }));
+    data
+}
+
+/// Custom data of an object or event with its v2 keys renamed, for v3 and v4
+pub(crate) fn object_custom_data_v3(beat: f64, data: &HashMap<String, serde_json::Value>, is_event: bool, warnings: &mut Vec<ConversionWarning>) -> HashMap<String, serde_json::Value> {
+    let (data, kept) = custom_data_from_v2(data, is_event);
+    warnings.extend(kept.into_iter().map(|key| ConversionWarning::CustomDataKept { beat, key }));
+    data
+}
+
 impl Beatmap {
     pub fn read_from_str(data: &str) -> Result<Self> {
         Ok(schema::BeatmapFile::read_from_str(data)?.into())
@@ -392,17 +541,212 @@ impl Beatmap {
     pub fn read_from_file(path: impl AsRef<Path>) -> Result<Self> {
         Self::read_from_str(&read_string_from_file(path)?)
     }
+
+    /// Serializes the beatmap in the v3 format, listing what couldn't be carried over from v2
+    pub fn write_to_string(&self) -> Result<(String, Vec<ConversionWarning>)> {
+        let (file, warnings) = self.to_v3_file();
+        Ok((schema::BeatmapFile::New(Box::new(file)).write_to_string()?, warnings))
+    }
+
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> Result<Vec<ConversionWarning>> {
+        let (data, warnings) = self.write_to_string()?;
+        write_string_to_file(path, &data)?;
+        Ok(warnings)
+    }
+
+    /// Serializes the beatmap in the legacy v2 format, listing everything that couldn't be represented in it
+    pub fn write_to_string_v2(&self) -> Result<(String, Vec<ConversionWarning>)> {
+        let (file, warnings) = self.to_v2_file();
+        Ok((schema::BeatmapFile::Old(Box::new(file)).write_to_string()?, warnings))
+    }
+
+    pub fn write_to_file_v2(&self, path: impl AsRef<Path>) -> Result<Vec<ConversionWarning>> {
+        let (data, warnings) = self.write_to_string_v2()?;
+        write_string_to_file(path, &data)?;
+        Ok(warnings)
+    }
+
+    /// Beatmap custom data with Heck's events and point definitions in their v2 form, or in their v3 form (also used by v4) if `to_v2` isn't set
+    pub(crate) fn custom_data_for(&self, to_v2: bool, warnings: &mut Vec<ConversionWarning>) -> HashMap<String, serde_json::Value> {
+        if self.version.starts_with('2') == to_v2 {
+            return self.custom_data.clone();
+        }
+        let mut target = Beatmap {
+            version: if to_v2 { "2.6.0" } else { "3.2.0" }.to_string(),
+            events: vec![],
+            waypoints: vec![],
+            basic_event_types_with_keywords: HashMap::new(),
+            use_normal_events_as_compatible_events: false,
+            custom_data: HashMap::new(),
+            extras: HashMap::new(),
+        };
+        let prefix = if to_v2 { "" } else { "_" };
+        for (key, value) in &self.custom_data {
+            let converted = match key.strip_prefix(prefix) {
+                Some("customEvents") => match self.custom_events() {
+                    Ok(events) => {
+                        warnings.extend(events.iter().flat_map(|event| event.properties_without_v2_name().into_iter()
+                            .map(|property| ConversionWarning::CustomEventPropertyKept { beat: event.beat, property: property.to_string() })));
+                        target.set_custom_events(&events);
+                        true
+                    },
+                    Err(_) => false,
+                },
+                Some("pointDefinitions") => self.point_definitions().map(|x| target.set_point_definitions(&x)).is_ok(),
+                _ => false,
+            };
+            if !converted {
+                if key.starts_with('_') != to_v2 {
+                    warnings.push(ConversionWarning::BeatmapCustomDataKept { key: key.clone() });
+                }
+                target.custom_data.insert(key.clone(), value.clone());
+            }
+        }
+        target.custom_data
+    }
+
+    pub fn to_v2_file(&self) -> (schema::OldBeatmapFile, Vec<ConversionWarning>) {
+        let mut warnings = vec![];
+        let mut file = schema::OldBeatmapFile {
+            _version: if self.version.starts_with('2') { self.version.clone() } else { "2.6.0".to_string() },
+            _notes: vec![],
+            _sliders: vec![],
+            _obstacles: vec![],
+            _events: vec![],
+            _waypoints: self.waypoints.clone(),
+            customData: self.custom_data_for(true, &mut warnings),
+            extras: self.extras.clone(),
+        };
+        if !self.basic_event_types_with_keywords.is_empty() {
+            warnings.push(ConversionWarning::KeywordsDropped);
+        }
+        for event in &self.events {
+            match event {
+                Event::BPM(event) => file._events.push(schema::OldEvent {
+                    _time: event.beat,
+                    _type: BasicEventType::BpmChange.into(),
+                    _value: 0,
+                    _floatValue: Some(event.value),
+                    _customData: HashMap::new(),
+                    extras: event.extras.clone(),
+                }),
+                Event::Rotation(event) => {
+                    let value = match LEGACY_ROTATION_VALUES.iter().position(|x| *x == event.value) {
+                        Some(index) => index as i32,
+                        None => {
+                            // Mapping Extensions encoding, whole degrees in -360..360
+                            let mut rounded_to = event.value.round();
+                            if rounded_to.abs() > 360.0 {
+                                rounded_to %= 360.0;
+                            }
+                            if rounded_to != event.value {
+                                warnings.push(ConversionWarning::RotationRounded { beat: event.beat, value: event.value, rounded_to });
+                            }
+                            warnings.push(ConversionWarning::RotationNeedsMappingExtensions { beat: event.beat, value: rounded_to });
+                            rounded_to as i32 + 1360
+                        }
+                    };
+                    file._events.push(schema::OldEvent {
+                        _time: event.beat,
+                        _type: if event.is_late { BasicEventType::LateRotation } else { BasicEventType::EarlyRotation }.into(),
+                        _value: value,
+                        _floatValue: None,
+                        _customData: HashMap::new(),
+                        extras: event.extras.clone(),
+                    })
+                },
+                Event::Note(note) => {
+                    if note.angle_offset != 0.0 {
+                        warnings.push(ConversionWarning::AngleOffsetDropped { beat: note.beat, angle_offset: note.angle_offset });
+                    }
+                    file._notes.push(schema::OldNote {
+                        _time: note.beat,
+                        _lineIndex: note.x,
+                        _lineLayer: note.y,
+                        _type: match note.color {
+                            NoteColor::Red => schema::OldNoteKind::Red,
+                            NoteColor::Blue => schema::OldNoteKind::Blue,
+                        },
+                        _cutDirection: note.direction,
+                        _customData: object_custom_data_v2(note.beat, &note.custom_data, &mut warnings),
+                        extras: note.extras.clone(),
+                    })
+                },
+                Event::Bomb(bomb) => file._notes.push(schema::OldNote {
+                    _time: bomb.beat,
+                    _lineIndex: bomb.x,
+                    _lineLayer: bomb.y,
+                    _type: schema::OldNoteKind::Bomb,
+                    _cutDirection: Direction::Any,
+                    _customData: object_custom_data_v2(bomb.beat, &bomb.custom_data, &mut warnings),
+                    extras: bomb.extras.clone(),
+                }),
+                Event::Obstacle(obstacle) => {
+                    let is_full = obstacle.y == 0;
+                    let is_exact = if is_full { obstacle.height == 5.0 } else { obstacle.y == 2 && (obstacle.height == 2.0 || obstacle.height == 3.0) };
+                    if !is_exact {
+                        warnings.push(ConversionWarning::ObstacleReshaped { beat: obstacle.beat });
+                    }
+                    file._obstacles.push(schema::OldObstacle {
+                        _time: obstacle.beat,
+                        _lineIndex: obstacle.x,
+                        _type: if is_full { schema::OldObstacleKind::Full } else { schema::OldObstacleKind::Crouch },
+                        _duration: obstacle.duration,
+                        _width: obstacle.width,
+                        _customData: object_custom_data_v2(obstacle.beat, &obstacle.custom_data, &mut warnings),
+                        extras: obstacle.extras.clone(),
+                    })
+                },
+                Event::Slider(slider) => file._sliders.push(schema::OldSlider {
+                    _colorType: slider.color,
+                    _headTime: slider.head_beat,
+                    _headLineIndex: slider.head_x,
+                    _headLineLayer: slider.head_y,
+                    _headControlPointLengthMultiplier: slider.head_bulge,
+                    _headCutDirection: slider.head_direction,
+                    _tailTime: slider.tail_beat,
+                    _tailLineIndex: slider.tail_x,
+                    _tailLineLayer: slider.tail_y,
+                    _tailControlPointLengthMultiplier: slider.tail_bulge,
+                    _tailCutDirection: slider.tail_direction,
+                    _sliderMidAnchorMode: slider.special_curving,
+                    _customData: object_custom_data_v2(slider.head_beat, &slider.custom_data, &mut warnings),
+                    extras: slider.extras.clone(),
+                }),
+                Event::BurstSlider(slider) => warnings.push(ConversionWarning::BurstSliderDropped { beat: slider.head_beat }),
+                Event::BasicEvent(event) => file._events.push(schema::OldEvent {
+                    _time: event.beat,
+                    _type: event.kind,
+                    _value: event.value,
+                    _floatValue: event.float_value,
+                    _customData: object_custom_data_v2(event.beat, &event.custom_data, &mut warnings),
+                    extras: event.extras.clone(),
+                }),
+                Event::ColorBoost(event) => file._events.push(schema::OldEvent {
+                    _time: event.beat,
+                    _type: BasicEventType::ColorBoost.into(),
+                    _value: event.enable as i32,
+                    _floatValue: None,
+                    _customData: HashMap::new(),
+                    extras: event.extras.clone(),
+                }),
+                Event::LightEventBox(event_box) => warnings.push(ConversionWarning::LightEventBoxDropped { beat: event_box.beat }),
+            }
+        }
+        (file, warnings)
+    }
 }
 
 impl From<schema::BeatmapFile> for Beatmap {
     fn from(file: schema::BeatmapFile) -> Self {
         match file {
             schema::BeatmapFile::Old(file) => {
+                let file = *file;
                 let mut events: Vec<Event> = vec![];
-                events.extend(file._notes.into_iter().filter_map(|e| Into::<Option<Event>>::into(e)));
-                events.extend(file._sliders.into_iter().map(|x| Into::<Event>::into(x)));
-                events.extend(file._obstacles.into_iter().map(|x| Into::<Event>::into(x)));
-                events.extend(file._events.into_iter().map(|x| Into::<Event>::into(x)));
+                events.extend(file._notes.into_iter().filter_map(Option::<Event>::from));
+                events.extend(file._sliders.into_iter().map(Event::from));
+                events.extend(file._obstacles.into_iter().map(Event::from));
+                events.extend(file._events.into_iter().map(Event::from));
 
                 Self {
                     version: file._version,
@@ -411,22 +755,24 @@ impl From<schema::BeatmapFile> for Beatmap {
                     basic_event_types_with_keywords: HashMap::new(),
                     use_normal_events_as_compatible_events: true,
                     custom_data: file.customData,
+                    extras: file.extras,
                 }
             },
             schema::BeatmapFile::New(file) => {
+                let file = *file;
                 let mut events: Vec<Event> = vec![];
-                events.extend(file.bpmEvents.into_iter().map(|x| Into::<Event>::into(x)));
-                events.extend(file.rotationEvents.into_iter().map(|x| Into::<Event>::into(x)));
-                events.extend(file.colorNotes.into_iter().map(|x| Into::<Event>::into(x)));
-                events.extend(file.bombNotes.into_iter().map(|x| Into::<Event>::into(x)));
-                events.extend(file.sliders.into_iter().map(|x| Into::<Event>::into(x)));
-                events.extend(file.obstacles.into_iter().map(|x| Into::<Event>::into(x)));
-                events.extend(file.burstSliders.into_iter().map(|x| Into::<Event>::into(x)));
-                events.extend(file.basicBeatmapEvents.into_iter().map(|x| Into::<Event>::into(x)));
-                events.extend(file.colorBoostBeatmapEvents.into_iter().map(|x| Into::<Event>::into(x)));
-                events.extend(file.lightColorEventBoxGroups.into_iter().map(|x| Into::<Event>::into(x)));
-                events.extend(file.lightRotationEventBoxGroups.into_iter().map(|x| Into::<Event>::into(x)));
-                events.extend(file.lightTranslationEventBoxGroups.into_iter().map(|x| Into::<Event>::into(x)));
+                events.extend(file.bpmEvents.into_iter().map(Event::from));
+                events.extend(file.rotationEvents.into_iter().map(Event::from));
+                events.extend(file.colorNotes.into_iter().map(Event::from));
+                events.extend(file.bombNotes.into_iter().map(Event::from));
+                events.extend(file.sliders.into_iter().map(Event::from));
+                events.extend(file.obstacles.into_iter().map(Event::from));
+                events.extend(file.burstSliders.into_iter().map(Event::from));
+                events.extend(file.basicBeatmapEvents.into_iter().map(Event::from));
+                events.extend(file.colorBoostBeatmapEvents.into_iter().map(Event::from));
+                events.extend(file.lightColorEventBoxGroups.into_iter().map(Event::from));
+                events.extend(file.lightRotationEventBoxGroups.into_iter().map(Event::from));
+                events.extend(file.lightTranslationEventBoxGroups.into_iter().map(Event::from));
                 Self {
                     version: file.version,
                     events,
@@ -434,9 +780,330 @@ impl From<schema::BeatmapFile> for Beatmap {
                     basic_event_types_with_keywords: file.basicEventTypesWithKeywords,
                     use_normal_events_as_compatible_events: file.useNormalEventsAsCompatibleEvents,
                     custom_data: file.customData,
+                    extras: file.extras,
                 }
+            },
+            schema::BeatmapFile::V4(file) => Self::from_v4(*file, None),
+        }
+
+    }
+}
+
+impl From<&BPMEvent> for schema::BpmEvent {
+    fn from(event: &BPMEvent) -> Self {
+        Self {
+            b: event.beat,
+            m: event.value,
+            extras: event.extras.clone(),
+        }
+    }
+}
+
+impl From<&Rotation> for schema::RotationEvent {
+    fn from(event: &Rotation) -> Self {
+        Self {
+            b: event.beat,
+            e: event.is_late,
+            r: event.value,
+            extras: event.extras.clone(),
+        }
+    }
+}
+
+impl From<&Note> for schema::ColorNote {
+    fn from(note: &Note) -> Self {
+        Self {
+            b: note.beat,
+            x: note.x,
+            y: note.y,
+            c: note.color,
+            d: note.direction,
+            a: note.angle_offset.round() as i32,
+            customData: note.custom_data.clone(),
+            extras: note.extras.clone(),
+        }
+    }
+}
+
+impl From<&Bomb> for schema::BombNote {
+    fn from(bomb: &Bomb) -> Self {
+        Self {
+            b: bomb.beat,
+            x: bomb.x,
+            y: bomb.y,
+            customData: bomb.custom_data.clone(),
+            extras: bomb.extras.clone(),
+        }
+    }
+}
+
+impl From<&Obstacle> for schema::Obstacle {
+    fn from(obstacle: &Obstacle) -> Self {
+        Self {
+            b: obstacle.beat,
+            x: obstacle.x,
+            y: obstacle.y,
+            d: obstacle.duration,
+            w: obstacle.width,
+            h: obstacle.height,
+            customData: obstacle.custom_data.clone(),
+            extras: obstacle.extras.clone(),
+        }
+    }
+}
+
+impl From<&Slider> for schema::Slider {
+    fn from(slider: &Slider) -> Self {
+        Self {
+            b: slider.head_beat,
+            c: slider.color,
+            x: slider.head_x,
+            y: slider.head_y,
+            d: slider.head_direction,
+            mu: slider.head_bulge,
+            tb: slider.tail_beat,
+            tx: slider.tail_x,
+            ty: slider.tail_y,
+            tc: slider.tail_direction,
+            tmu: slider.tail_bulge,
+            m: slider.special_curving,
+            customData: slider.custom_data.clone(),
+            extras: slider.extras.clone(),
+        }
+    }
+}
+
+impl From<&BurstSlider> for schema::BurstSlider {
+    fn from(slider: &BurstSlider) -> Self {
+        Self {
+            b: slider.head_beat,
+            c: slider.color,
+            x: slider.head_x,
+            y: slider.head_y,
+            d: slider.head_direction,
+            tb: slider.tail_beat,
+            tx: slider.tail_x,
+            ty: slider.tail_y,
+            sc: slider.segment_count,
+            s: slider.squish,
+            customData: slider.custom_data.clone(),
+            extras: slider.extras.clone(),
+        }
+    }
+}
+
+impl From<&BasicEvent> for schema::BasicBeatmapEvent {
+    fn from(event: &BasicEvent) -> Self {
+        Self {
+            b: event.beat,
+            et: event.kind,
+            i: event.value,
+            f: event.float_value,
+            customData: event.custom_data.clone(),
+            extras: event.extras.clone(),
+        }
+    }
+}
+
+impl From<&ColorBoost> for schema::ColorBoostBeatmapEvent {
+    fn from(event: &ColorBoost) -> Self {
+        Self {
+            b: event.beat,
+            o: event.enable,
+            extras: event.extras.clone(),
+        }
+    }
+}
+
+impl From<&LightColorEvent> for schema::LightColorEventData {
+    fn from(event: &LightColorEvent) -> Self {
+        Self {
+            b: event.relative_beat,
+            i: event.transition_kind,
+            c: event.color,
+            s: event.brightness,
+            f: event.frequency,
+            extras: event.extras.clone(),
+        }
+    }
+}
+
+impl From<&LightRotationEvent> for schema::LightRotationEventData {
+    fn from(event: &LightRotationEvent) -> Self {
+        Self {
+            b: event.relative_beat,
+            p: event.behaviour,
+            l: event.loops,
+            e: event.easing,
+            r: event.amount,
+            o: event.direction,
+            extras: event.extras.clone(),
+        }
+    }
+}
+
+impl From<&LightTranslationEvent> for schema::LightTranslationEventData {
+    fn from(event: &LightTranslationEvent) -> Self {
+        Self {
+            b: event.relative_beat,
+            p: event.rotation_behaviour,
+            e: event.easing,
+            t: event.amount,
+            extras: event.extras.clone(),
+        }
+    }
+}
+
+impl From<&BoxFilter> for schema::FilterObject {
+    fn from(filter: &BoxFilter) -> Self {
+        let (f, p, t) = match filter.settings {
+            BoxFilterSettings::Sections { count, index } => (BoxFilterKind::Sections, count, index),
+            BoxFilterSettings::StepAndOffset { start, skip } => (BoxFilterKind::StepAndOffset, start, skip),
+        };
+        Self {
+            c: filter.chunks,
+            f,
+            p,
+            t,
+            r: filter.reverse,
+            n: filter.ordering,
+            s: filter.random_seed,
+            l: filter.limit,
+            d: filter.limit_kind,
+            extras: filter.extras.clone(),
+        }
+    }
+}
+
+impl schema::NewBeatmapFile {
+    /// A single [LightEventBox] may hold lanes of different kinds, so it is split into one group per kind
+    fn push_light_event_box(&mut self, event_box: &LightEventBox) {
+        let mut color_lanes = vec![];
+        let mut rotation_lanes = vec![];
+        let mut translation_lanes = vec![];
+        for lane in &event_box.lanes {
+            match &lane.events {
+                LightEvents::Color(events) => color_lanes.push(schema::LightColorEventBoxGroupLane {
+                    f: (&lane.filter).into(),
+                    w: lane.beat_dist,
+                    d: lane.beat_dist_kind,
+                    r: lane.dist,
+                    t: lane.dist_kind,
+                    b: lane.dist_affects_first_event,
+                    i: lane.dist_easing,
+                    e: events.iter().map(schema::LightColorEventData::from).collect(),
+                    extras: lane.extras.clone(),
+                }),
+                LightEvents::Rotation(events) => rotation_lanes.push(schema::LightRotationEventBoxGroupLane {
+                    f: (&lane.filter).into(),
+                    w: lane.beat_dist,
+                    d: lane.beat_dist_kind,
+                    s: lane.dist,
+                    t: lane.dist_kind,
+                    b: lane.dist_affects_first_event,
+                    i: lane.dist_easing,
+                    a: lane.axis.unwrap_or(Axis::X),
+                    r: lane.reverse.unwrap_or(false),
+                    e: events.iter().map(schema::LightRotationEventData::from).collect(),
+                    extras: lane.extras.clone(),
+                }),
+                LightEvents::Translation(events) => translation_lanes.push(schema::LightTranslationEventBoxGroupLane {
+                    f: (&lane.filter).into(),
+                    w: lane.beat_dist,
+                    d: lane.beat_dist_kind,
+                    s: lane.dist,
+                    t: lane.dist_kind,
+                    b: lane.dist_affects_first_event,
+                    i: lane.dist_easing,
+                    a: lane.axis.unwrap_or(Axis::X),
+                    r: lane.reverse.unwrap_or(false),
+                    l: events.iter().map(schema::LightTranslationEventData::from).collect(),
+                    extras: lane.extras.clone(),
+                }),
             }
         }
 
+        let is_empty = event_box.lanes.is_empty();
+        if !color_lanes.is_empty() || is_empty {
+            self.lightColorEventBoxGroups.push(schema::LightColorEventBoxGroup {
+                b: event_box.beat,
+                g: event_box.group,
+                e: color_lanes,
+                extras: event_box.extras.clone(),
+            });
+        }
+        if !rotation_lanes.is_empty() {
+            self.lightRotationEventBoxGroups.push(schema::LightRotationEventBoxGroup {
+                b: event_box.beat,
+                g: event_box.group,
+                e: rotation_lanes,
+                extras: event_box.extras.clone(),
+            });
+        }
+        if !translation_lanes.is_empty() {
+            self.lightTranslationEventBoxGroups.push(schema::LightTranslationEventBoxGroup {
+                b: event_box.beat,
+                g: event_box.group,
+                e: translation_lanes,
+                extras: event_box.extras.clone(),
+            });
+        }
+    }
+}
+
+impl Beatmap {
+    /// Converts the beatmap into a v3 file, renaming v2 custom data keys and converting Heck's v2 events and point definitions. Returns the keys that have no v3 name
+    pub fn to_v3_file(&self) -> (schema::NewBeatmapFile, Vec<ConversionWarning>) {
+        let mut warnings = vec![];
+        let mut file = schema::NewBeatmapFile {
+            version: if self.version.starts_with('3') { self.version.clone() } else { "3.2.0".to_string() },
+            bpmEvents: vec![],
+            rotationEvents: vec![],
+            colorNotes: vec![],
+            bombNotes: vec![],
+            obstacles: vec![],
+            sliders: vec![],
+            burstSliders: vec![],
+            waypoints: self.waypoints.clone(),
+            basicBeatmapEvents: vec![],
+            colorBoostBeatmapEvents: vec![],
+            lightColorEventBoxGroups: vec![],
+            lightRotationEventBoxGroups: vec![],
+            lightTranslationEventBoxGroups: vec![],
+            basicEventTypesWithKeywords: self.basic_event_types_with_keywords.clone(),
+            useNormalEventsAsCompatibleEvents: self.use_normal_events_as_compatible_events,
+            customData: self.custom_data_for(false, &mut warnings),
+            extras: self.extras.clone(),
+        };
+        for event in &self.events {
+            match event {
+                Event::BPM(event) => file.bpmEvents.push(event.as_ref().into()),
+                Event::Rotation(event) => file.rotationEvents.push(event.as_ref().into()),
+                Event::Note(note) => file.colorNotes.push(schema::ColorNote {
+                    customData: object_custom_data_v3(note.beat, &note.custom_data, false, &mut warnings),
+                    ..note.as_ref().into()
+                }),
+                Event::Bomb(bomb) => file.bombNotes.push(schema::BombNote {
+                    customData: object_custom_data_v3(bomb.beat, &bomb.custom_data, false, &mut warnings),
+                    ..bomb.as_ref().into()
+                }),
+                Event::Obstacle(obstacle) => file.obstacles.push(schema::Obstacle {
+                    customData: object_custom_data_v3(obstacle.beat, &obstacle.custom_data, false, &mut warnings),
+                    ..obstacle.as_ref().into()
+                }),
+                Event::Slider(slider) => file.sliders.push(schema::Slider {
+                    customData: object_custom_data_v3(slider.head_beat, &slider.custom_data, false, &mut warnings),
+                    ..slider.as_ref().into()
+                }),
+                Event::BurstSlider(slider) => file.burstSliders.push(slider.as_ref().into()),
+                Event::BasicEvent(event) => file.basicBeatmapEvents.push(schema::BasicBeatmapEvent {
+                    customData: object_custom_data_v3(event.beat, &event.custom_data, true, &mut warnings),
+                    ..event.as_ref().into()
+                }),
+                Event::ColorBoost(event) => file.colorBoostBeatmapEvents.push(event.as_ref().into()),
+                Event::LightEventBox(event_box) => file.push_light_event_box(event_box),
+            }
+        }
+        (file, warnings)
     }
 }
@@ -0,0 +1,817 @@
+//! Conversion between [Beatmap] and the v4 difficulty and lightshow files, between [BeatmapSetMeta] and the v4 `Info.dat`, and of [AudioData]
+
+use std::collections::HashMap;
+use std::path::Path;
+use serde::Serialize;
+use serde_json::json;
+use crate::error::{ConversionWarning, Result};
+use crate::types::common::{Axis, Direction, Easing, TransitionKind};
+use crate::types::lightning::{LightColorEvent, LightEventBox, LightEventLane, LightEvents, LightRotationEvent, LightTranslationEvent};
+use crate::types::common::{read_string_from_file, write_string_to_file};
+use crate::types::primary::{AudioData, BasicEvent, BeatmapMeta, BeatmapSetMeta, Bomb, BurstSlider, ColorBoost, ColorScheme, DifficultySet, Event, LufsRegion, Note, Obstacle, Rotation, SampleRegion, Slider};
+use crate::types::schema;
+use crate::types::converters::object_custom_data_v3;
+use crate::types::songcore::songcore_keys_to_version;
+use crate::Beatmap;
+
+/// Deduplicates the v4 data arrays, the way v4 stores repeated objects and events once. Items are keyed by array and serialized contents
+#[derive(Default)]
+struct Interner(HashMap<(&'static str, String), usize>);
+
+impl Interner {
+    /// Index of `item` in `data`, appended if it isn't there yet
+    fn intern<T: Serialize>(&mut self, array: &'static str, data: &mut Vec<T>, item: T) -> usize {
+        let Ok(key) = serde_json::to_string(&item) else {
+            data.push(item);
+            return data.len() - 1;
+        };
+        *self.0.entry((array, key)).or_insert_with(|| {
+            data.push(item);
+            data.len() - 1
+        })
+    }
+}
+
+/// Cumulative rotation at each beat, which v4 stores on every object
+struct RotationLanes(Vec<(f64, bool, f64)>);
+
+impl RotationLanes {
+    fn new(beatmap: &Beatmap) -> Self {
+        Self(beatmap.events.iter().filter_map(|x| match x {
+            Event::Rotation(event) => Some((event.beat, event.is_late, event.value)),
+            _ => None
+        }).collect())
+    }
+
+    /// Early rotations affect objects on the same beat, late ones only the objects after
+    fn at(&self, beat: f64) -> i32 {
+        self.0.iter()
+            .filter(|(time, is_late, _)| if *is_late { *time < beat } else { *time <= beat })
+            .map(|(_, _, value)| value)
+            .sum::<f64>()
+            .round() as i32
+    }
+}
+
+fn note_data(x: i32, y: i32, color: crate::types::common::NoteColor, direction: Direction) -> schema::V4ColorNoteData {
+    schema::V4ColorNoteData { x, y, c: color, d: direction, a: 0, customData: HashMap::new(), extras: HashMap::new() }
+}
+
+fn object(beat: f64, lane: i32, index: usize, extras: &HashMap<String, serde_json::Value>) -> schema::V4Object {
+    schema::V4Object { b: beat, r: lane, i: index, extras: extras.clone() }
+}
+
+fn indexed(beat: f64, index: usize) -> schema::V4Event {
+    schema::V4Event { b: beat, i: index, extras: HashMap::new() }
+}
+
+fn color_event_from_v4(relative_beat: f64, event: &schema::V4LightColorEventData) -> LightColorEvent {
+    LightColorEvent {
+        relative_beat,
+        transition_kind: if event.p == 1 {
+            TransitionKind::Extend
+        } else if event.e == Easing::None {
+            TransitionKind::Instant
+        } else {
+            TransitionKind::Transition
+        },
+        color: event.c,
+        brightness: event.b,
+        frequency: event.f,
+        extras: HashMap::new(),
+    }
+}
+
+fn color_event_to_v4(event: &LightColorEvent) -> schema::V4LightColorEventData {
+    let (p, e) = match event.transition_kind {
+        TransitionKind::Instant => (0, Easing::None),
+        TransitionKind::Transition => (0, Easing::Linear),
+        TransitionKind::Extend => (1, Easing::Linear),
+    };
+    schema::V4LightColorEventData { p, c: event.color, e, b: event.brightness, f: event.frequency, sb: 0.0, sf: 0, extras: HashMap::new() }
+}
+
+fn lane_from_v4(kind: schema::V4EventBoxGroupKind, event_box: &schema::V4EventBox, lightshow: &schema::V4LightshowFile) -> Option<LightEventLane> {
+    let filter = lightshow.indexFilters.get(event_box.f)?.clone().into();
+    let lane = match kind {
+        schema::V4EventBoxGroupKind::Color => {
+            let data = lightshow.lightColorEventBoxes.get(event_box.e)?;
+            let events = event_box.l.iter()
+                .filter_map(|x| Some(color_event_from_v4(x.b, lightshow.lightColorEvents.get(x.i)?)))
+                .collect();
+            LightEventLane {
+                filter,
+                beat_dist: data.w,
+                beat_dist_kind: data.d,
+                dist: data.s,
+                dist_kind: data.t,
+                dist_affects_first_event: data.b,
+                dist_easing: Some(data.e),
+                axis: None,
+                reverse: None,
+                events: LightEvents::Color(events),
+                extras: event_box.extras.clone(),
+            }
+        },
+        schema::V4EventBoxGroupKind::Rotation | schema::V4EventBoxGroupKind::Translation => {
+            let is_rotation = kind == schema::V4EventBoxGroupKind::Rotation;
+            let boxes = if is_rotation { &lightshow.lightRotationEventBoxes } else { &lightshow.lightTranslationEventBoxes };
+            let data = boxes.get(event_box.e)?;
+            let events = if is_rotation {
+                LightEvents::Rotation(event_box.l.iter().filter_map(|x| {
+                    let event = lightshow.lightRotationEvents.get(x.i)?;
+                    Some(LightRotationEvent { relative_beat: x.b, behaviour: event.p, easing: event.e, loops: event.l, amount: event.r, direction: event.d, extras: HashMap::new() })
+                }).collect())
+            } else {
+                LightEvents::Translation(event_box.l.iter().filter_map(|x| {
+                    let event = lightshow.lightTranslationEvents.get(x.i)?;
+                    Some(LightTranslationEvent { relative_beat: x.b, rotation_behaviour: event.p, easing: event.e, amount: event.t, extras: HashMap::new() })
+                }).collect())
+            };
+            LightEventLane {
+                filter,
+                beat_dist: data.w,
+                beat_dist_kind: data.d,
+                dist: data.s,
+                dist_kind: data.t,
+                dist_affects_first_event: data.b,
+                dist_easing: Some(data.e),
+                axis: Some(data.a),
+                reverse: Some(data.f),
+                events,
+                extras: event_box.extras.clone(),
+            }
+        },
+        schema::V4EventBoxGroupKind::FloatFx => return None,
+    };
+    Some(lane)
+}
+
+impl schema::V4LightshowFile {
+    fn push_light_event_box(&mut self, event_box: &LightEventBox, interner: &mut Interner) {
+        let mut groups: Vec<schema::V4EventBoxGroup> = vec![];
+        for lane in &event_box.lanes {
+            let f = interner.intern("indexFilters", &mut self.indexFilters, (&lane.filter).into());
+            let axis_data = |flip| schema::V4AxisEventBoxData {
+                w: lane.beat_dist,
+                d: lane.beat_dist_kind,
+                s: lane.dist,
+                t: lane.dist_kind,
+                b: lane.dist_affects_first_event,
+                e: lane.dist_easing.unwrap_or(Easing::Linear),
+                a: lane.axis.unwrap_or(Axis::X),
+                f: flip,
+                extras: HashMap::new(),
+            };
+            let (kind, e, l) = match &lane.events {
+                LightEvents::Color(events) => {
+                    let e = interner.intern("lightColorEventBoxes", &mut self.lightColorEventBoxes, schema::V4EventBoxData {
+                        w: lane.beat_dist,
+                        d: lane.beat_dist_kind,
+                        s: lane.dist,
+                        t: lane.dist_kind,
+                        b: lane.dist_affects_first_event,
+                        e: lane.dist_easing.unwrap_or(Easing::Linear),
+                        extras: HashMap::new(),
+                    });
+                    let l = events.iter()
+                        .map(|x| indexed(x.relative_beat, interner.intern("lightColorEvents", &mut self.lightColorEvents, color_event_to_v4(x))))
+                        .collect();
+                    (schema::V4EventBoxGroupKind::Color, e, l)
+                },
+                LightEvents::Rotation(events) => {
+                    let e = interner.intern("lightRotationEventBoxes", &mut self.lightRotationEventBoxes, axis_data(lane.reverse.unwrap_or(false)));
+                    let l = events.iter().map(|x| {
+                        let data = schema::V4LightRotationEventData { p: x.behaviour, e: x.easing, r: x.amount, d: x.direction, l: x.loops, extras: HashMap::new() };
+                        indexed(x.relative_beat, interner.intern("lightRotationEvents", &mut self.lightRotationEvents, data))
+                    }).collect();
+                    (schema::V4EventBoxGroupKind::Rotation, e, l)
+                },
+                LightEvents::Translation(events) => {
+                    let e = interner.intern("lightTranslationEventBoxes", &mut self.lightTranslationEventBoxes, axis_data(lane.reverse.unwrap_or(false)));
+                    let l = events.iter().map(|x| {
+                        let data = schema::V4LightTranslationEventData { p: x.rotation_behaviour, e: x.easing, t: x.amount, extras: HashMap::new() };
+                        indexed(x.relative_beat, interner.intern("lightTranslationEvents", &mut self.lightTranslationEvents, data))
+                    }).collect();
+                    (schema::V4EventBoxGroupKind::Translation, e, l)
+                },
+            };
+            let v4_box = schema::V4EventBox { f, e, l, extras: lane.extras.clone() };
+            match groups.iter_mut().find(|x| x.t == kind) {
+                Some(group) => group.e.push(v4_box),
+                None => groups.push(schema::V4EventBoxGroup { t: kind, b: event_box.beat, g: event_box.group, e: vec![v4_box], extras: event_box.extras.clone() }),
+            }
+        }
+        if groups.is_empty() {
+            groups.push(schema::V4EventBoxGroup { t: schema::V4EventBoxGroupKind::Color, b: event_box.beat, g: event_box.group, e: vec![], extras: event_box.extras.clone() });
+        }
+        self.eventBoxGroups.extend(groups);
+    }
+}
+
+impl Beatmap {
+    /// Builds a beatmap from a v4 difficulty file and, optionally, its lightshow file. v4 keeps BPM changes in the audio file, so there won't be any.
+    /// Float FX event boxes, strobe settings of color events and objects pointing past the end of their data array are dropped
+    pub fn from_v4(file: schema::V4BeatmapFile, lightshow: Option<schema::V4LightshowFile>) -> Self {
+        let mut events: Vec<Event> = vec![];
+        events.extend(file.spawnRotations.iter().filter_map(|x| {
+            let data = file.spawnRotationsData.get(x.i)?;
+            Some(Event::Rotation(Box::new(Rotation { beat: x.b, is_late: data.t == 1, value: data.r, extras: x.extras.clone() })))
+        }));
+        events.extend(file.colorNotes.iter().filter_map(|x| {
+            let data = file.colorNotesData.get(x.i)?;
+            Some(Event::Note(Box::new(Note {
+                beat: x.b,
+                x: data.x,
+                y: data.y,
+                color: data.c,
+                direction: data.d,
+                angle_offset: data.a as f64,
+                custom_data: data.customData.clone(),
+                extras: x.extras.clone(),
+            })))
+        }));
+        events.extend(file.bombNotes.iter().filter_map(|x| {
+            let data = file.bombNotesData.get(x.i)?;
+            Some(Event::Bomb(Box::new(Bomb { beat: x.b, x: data.x, y: data.y, custom_data: data.customData.clone(), extras: x.extras.clone() })))
+        }));
+        events.extend(file.obstacles.iter().filter_map(|x| {
+            let data = file.obstaclesData.get(x.i)?;
+            Some(Event::Obstacle(Box::new(Obstacle {
+                beat: x.b,
+                x: data.x,
+                y: data.y,
+                duration: data.d,
+                width: data.w,
+                height: data.h,
+                custom_data: data.customData.clone(),
+                extras: x.extras.clone(),
+            })))
+        }));
+        events.extend(file.arcs.iter().filter_map(|x| {
+            let head = file.colorNotesData.get(x.hi)?;
+            let tail = file.colorNotesData.get(x.ti)?;
+            let data = file.arcsData.get(x.ai)?;
+            Some(Event::Slider(Box::new(Slider {
+                head_beat: x.hb,
+                color: head.c,
+                head_x: head.x,
+                head_y: head.y,
+                head_direction: head.d,
+                head_bulge: data.m,
+                tail_beat: x.tb,
+                tail_x: tail.x,
+                tail_y: tail.y,
+                tail_direction: tail.d,
+                tail_bulge: data.tm,
+                special_curving: data.a,
+                custom_data: data.customData.clone(),
+                extras: x.extras.clone(),
+            })))
+        }));
+        events.extend(file.chains.iter().filter_map(|x| {
+            let head = file.colorNotesData.get(x.i)?;
+            let data = file.chainsData.get(x.ci)?;
+            Some(Event::BurstSlider(Box::new(BurstSlider {
+                head_beat: x.hb,
+                color: head.c,
+                head_x: head.x,
+                head_y: head.y,
+                head_direction: head.d,
+                tail_beat: x.tb,
+                tail_x: data.tx,
+                tail_y: data.ty,
+                segment_count: data.c,
+                squish: data.s,
+                custom_data: data.customData.clone(),
+                extras: x.extras.clone(),
+            })))
+        }));
+
+        let mut beatmap = Self {
+            version: file.version,
+            events,
+            waypoints: vec![],
+            basic_event_types_with_keywords: HashMap::new(),
+            use_normal_events_as_compatible_events: false,
+            custom_data: file.customData,
+            extras: file.extras,
+        };
+        let Some(lightshow) = lightshow else { return beatmap };
+
+        beatmap.waypoints = lightshow.waypoints.iter().filter_map(|x| {
+            let data = lightshow.waypointsData.get(x.i)?;
+            Some(json!({ "b": x.b, "x": data.x, "y": data.y, "d": data.d }))
+        }).collect();
+        beatmap.events.extend(lightshow.basicEvents.iter().filter_map(|x| {
+            let data = lightshow.basicEventsData.get(x.i)?;
+            Some(Event::BasicEvent(Box::new(BasicEvent {
+                beat: x.b,
+                kind: data.t,
+                value: data.i,
+                float_value: data.f,
+                custom_data: data.customData.clone(),
+                extras: x.extras.clone(),
+            })))
+        }));
+        beatmap.events.extend(lightshow.colorBoostEvents.iter().filter_map(|x| {
+            let data = lightshow.colorBoostEventsData.get(x.i)?;
+            Some(Event::ColorBoost(Box::new(ColorBoost { beat: x.b, enable: data.b, extras: x.extras.clone() })))
+        }));
+        beatmap.events.extend(lightshow.eventBoxGroups.iter().filter(|x| x.t != schema::V4EventBoxGroupKind::FloatFx).map(|group| {
+            Event::LightEventBox(Box::new(LightEventBox {
+                beat: group.b,
+                group: group.g,
+                lanes: group.e.iter().filter_map(|x| lane_from_v4(group.t, x, &lightshow)).collect(),
+                extras: group.extras.clone(),
+            }))
+        }));
+        beatmap.basic_event_types_with_keywords = lightshow.basicEventTypesWithKeywords;
+        beatmap.use_normal_events_as_compatible_events = lightshow.useNormalEventsAsCompatibleEvents;
+        for (key, value) in lightshow.customData {
+            beatmap.custom_data.entry(key).or_insert(value);
+        }
+        beatmap
+    }
+
+    /// Reads a v4 difficulty and its lightshow. v2 and v3 files are read as usual, ignoring the lightshow
+    pub fn read_from_str_v4(beatmap: &str, lightshow: Option<&str>) -> Result<Self> {
+        match schema::BeatmapFile::read_from_str(beatmap)? {
+            schema::BeatmapFile::V4(file) => {
+                let lightshow = lightshow.map(schema::V4LightshowFile::read_from_str).transpose()?;
+                Ok(Self::from_v4(*file, lightshow))
+            },
+            file => Ok(file.into()),
+        }
+    }
+
+    /// Converts the beatmap into a v4 difficulty file and a lightshow file. Returns everything that couldn't be represented
+    pub fn to_v4_files(&self) -> (schema::V4BeatmapFile, schema::V4LightshowFile, Vec<ConversionWarning>) {
+        let version = if self.version.starts_with('4') { self.version.clone() } else { "4.0.0".to_string() };
+        let mut warnings = vec![];
+        let mut interner = Interner::default();
+        let mut file = schema::V4BeatmapFile {
+            version: version.clone(),
+            colorNotes: vec![],
+            colorNotesData: vec![],
+            bombNotes: vec![],
+            bombNotesData: vec![],
+            obstacles: vec![],
+            obstaclesData: vec![],
+            arcs: vec![],
+            arcsData: vec![],
+            chains: vec![],
+            chainsData: vec![],
+            spawnRotations: vec![],
+            spawnRotationsData: vec![],
+            customData: self.custom_data_for(false, &mut warnings),
+            extras: self.extras.clone(),
+        };
+        let mut lightshow = schema::V4LightshowFile {
+            version,
+            waypoints: vec![],
+            waypointsData: vec![],
+            basicEvents: vec![],
+            basicEventsData: vec![],
+            colorBoostEvents: vec![],
+            colorBoostEventsData: vec![],
+            eventBoxGroups: vec![],
+            indexFilters: vec![],
+            lightColorEventBoxes: vec![],
+            lightColorEvents: vec![],
+            lightRotationEventBoxes: vec![],
+            lightRotationEvents: vec![],
+            lightTranslationEventBoxes: vec![],
+            lightTranslationEvents: vec![],
+            basicEventTypesWithKeywords: self.basic_event_types_with_keywords.clone(),
+            useNormalEventsAsCompatibleEvents: self.use_normal_events_as_compatible_events,
+            customData: HashMap::new(),
+            extras: HashMap::new(),
+        };
+
+        let lanes = RotationLanes::new(self);
+        for event in &self.events {
+            match event {
+                Event::BPM(event) => warnings.push(ConversionWarning::BpmEventDropped { beat: event.beat }),
+                Event::Rotation(rotation) => {
+                    let data = schema::V4SpawnRotationData { t: rotation.is_late as i32, r: rotation.value, extras: HashMap::new() };
+                    file.spawnRotations.push(schema::V4Event { extras: rotation.extras.clone(), ..indexed(rotation.beat, interner.intern("spawnRotationsData", &mut file.spawnRotationsData, data)) });
+                },
+                Event::Note(note) => {
+                    let data = schema::V4ColorNoteData {
+                        a: note.angle_offset.round() as i32,
+                        customData: object_custom_data_v3(note.beat, &note.custom_data, false, &mut warnings),
+                        ..note_data(note.x, note.y, note.color, note.direction)
+                    };
+                    file.colorNotes.push(object(note.beat, lanes.at(note.beat), interner.intern("colorNotesData", &mut file.colorNotesData, data), &note.extras));
+                },
+                Event::Bomb(bomb) => {
+                    let data = schema::V4BombNoteData { x: bomb.x, y: bomb.y, customData: object_custom_data_v3(bomb.beat, &bomb.custom_data, false, &mut warnings), extras: HashMap::new() };
+                    file.bombNotes.push(object(bomb.beat, lanes.at(bomb.beat), interner.intern("bombNotesData", &mut file.bombNotesData, data), &bomb.extras));
+                },
+                Event::Obstacle(obstacle) => {
+                    let data = schema::V4ObstacleData {
+                        d: obstacle.duration,
+                        x: obstacle.x,
+                        y: obstacle.y,
+                        w: obstacle.width,
+                        h: obstacle.height,
+                        customData: object_custom_data_v3(obstacle.beat, &obstacle.custom_data, false, &mut warnings),
+                        extras: HashMap::new(),
+                    };
+                    file.obstacles.push(object(obstacle.beat, lanes.at(obstacle.beat), interner.intern("obstaclesData", &mut file.obstaclesData, data), &obstacle.extras));
+                },
+                Event::Slider(slider) => {
+                    let hi = interner.intern("colorNotesData", &mut file.colorNotesData, note_data(slider.head_x, slider.head_y, slider.color, slider.head_direction));
+                    let ti = interner.intern("colorNotesData", &mut file.colorNotesData, note_data(slider.tail_x, slider.tail_y, slider.color, slider.tail_direction));
+                    let data = schema::V4ArcData {
+                        m: slider.head_bulge,
+                        tm: slider.tail_bulge,
+                        a: slider.special_curving,
+                        customData: object_custom_data_v3(slider.head_beat, &slider.custom_data, false, &mut warnings),
+                        extras: HashMap::new(),
+                    };
+                    file.arcs.push(schema::V4Arc {
+                        hb: slider.head_beat,
+                        tb: slider.tail_beat,
+                        hr: lanes.at(slider.head_beat),
+                        tr: lanes.at(slider.tail_beat),
+                        hi,
+                        ti,
+                        ai: interner.intern("arcsData", &mut file.arcsData, data),
+                        extras: slider.extras.clone(),
+                    });
+                },
+                Event::BurstSlider(slider) => {
+                    let i = interner.intern("colorNotesData", &mut file.colorNotesData, note_data(slider.head_x, slider.head_y, slider.color, slider.head_direction));
+                    let data = schema::V4ChainData {
+                        tx: slider.tail_x,
+                        ty: slider.tail_y,
+                        c: slider.segment_count,
+                        s: slider.squish,
+                        customData: slider.custom_data.clone(),
+                        extras: HashMap::new(),
+                    };
+                    file.chains.push(schema::V4Chain {
+                        hb: slider.head_beat,
+                        tb: slider.tail_beat,
+                        hr: lanes.at(slider.head_beat),
+                        tr: lanes.at(slider.tail_beat),
+                        i,
+                        ci: interner.intern("chainsData", &mut file.chainsData, data),
+                        extras: slider.extras.clone(),
+                    });
+                },
+                Event::BasicEvent(basic) => {
+                    let data = schema::V4BasicEventData {
+                        t: basic.kind,
+                        i: basic.value,
+                        f: basic.float_value,
+                        customData: object_custom_data_v3(basic.beat, &basic.custom_data, true, &mut warnings),
+                        extras: HashMap::new(),
+                    };
+                    lightshow.basicEvents.push(schema::V4Event { extras: basic.extras.clone(), ..indexed(basic.beat, interner.intern("basicEventsData", &mut lightshow.basicEventsData, data)) });
+                },
+                Event::ColorBoost(boost) => {
+                    let data = schema::V4ColorBoostEventData { b: boost.enable, extras: HashMap::new() };
+                    lightshow.colorBoostEvents.push(schema::V4Event { extras: boost.extras.clone(), ..indexed(boost.beat, interner.intern("colorBoostEventsData", &mut lightshow.colorBoostEventsData, data)) });
+                },
+                Event::LightEventBox(event_box) => lightshow.push_light_event_box(event_box, &mut interner),
+            }
+        }
+        for waypoint in &self.waypoints {
+            let field = |key: &str| waypoint.get(key).and_then(|x| x.as_i64()).map(|x| x as i32);
+            if let (Some(beat), Some(x), Some(y), Some(d)) = (waypoint.get("b").and_then(|x| x.as_f64()), field("x"), field("y"), field("d")) {
+                let data = schema::V4WaypointData { x, y, d, extras: HashMap::new() };
+                lightshow.waypoints.push(indexed(beat, interner.intern("waypointsData", &mut lightshow.waypointsData, data)));
+            }
+        }
+        (file, lightshow, warnings)
+    }
+
+    /// Serializes the beatmap in the v4 format, as the difficulty and the lightshow file
+    pub fn write_to_strings_v4(&self) -> Result<(String, String, Vec<ConversionWarning>)> {
+        let (file, lightshow, warnings) = self.to_v4_files();
+        Ok((schema::BeatmapFile::V4(Box::new(file)).write_to_string()?, lightshow.write_to_string()?, warnings))
+    }
+}
+
+impl From<schema::V4Info> for BeatmapSetMeta {
+    fn from(info: schema::V4Info) -> Self {
+        let all_directions_environment_name = info.difficultyBeatmaps.iter()
+            .find(|x| is_all_directions(&x.characteristic))
+            .and_then(|x| info.environmentNames.get(x.environmentNameIdx).cloned());
+        let mut map_authors: Vec<String> = vec![];
+        let mut difficulty_sets: Vec<DifficultySet> = vec![];
+        for beatmap in info.difficultyBeatmaps {
+            for mapper in &beatmap.beatmapAuthors.mappers {
+                if !map_authors.contains(mapper) {
+                    map_authors.push(mapper.clone());
+                }
+            }
+            let meta = BeatmapMeta {
+                difficulty: beatmap.difficulty,
+                rank: beatmap.difficulty.rank(),
+                filename: beatmap.beatmapDataFilename,
+                note_jump_speed: beatmap.noteJumpMovementSpeed,
+                note_jump_start_beat_offset: beatmap.noteJumpStartBeatOffset,
+                mappers: beatmap.beatmapAuthors.mappers,
+                lighters: beatmap.beatmapAuthors.lighters,
+                environment_name: info.environmentNames.get(beatmap.environmentNameIdx).cloned(),
+                color_scheme: if info.colorSchemes.is_empty() { None } else { Some(beatmap.beatmapColorSchemeIdx) },
+                lightshow_filename: Some(beatmap.lightshowDataFilename).filter(|x| !x.is_empty()),
+                custom_data: beatmap.customData,
+                extras: beatmap.extras,
+            };
+            match difficulty_sets.iter_mut().find(|x| x.game_mode == beatmap.characteristic) {
+                Some(set) => set.beatmaps.push(meta),
+                None => difficulty_sets.push(DifficultySet { game_mode: beatmap.characteristic, beatmaps: vec![meta], extras: HashMap::new() }),
+            }
+        }
+
+        Self {
+            version: info.version,
+            song_name: info.song.title,
+            song_subname: info.song.subTitle,
+            song_author: info.song.author,
+            map_author: map_authors.join(", "),
+            bpm: info.audio.bpm,
+            shuffle: 0.0,
+            shuffle_period: 0.5,
+            preview_start: info.audio.previewStartTime,
+            preview_duration: info.audio.previewDuration,
+            song_filename: info.audio.songFilename,
+            cover_image_filename: info.coverImageFilename,
+            environment_name: info.environmentNames.first().cloned().unwrap_or_default(),
+            all_directions_environment_name,
+            song_offset: 0.0,
+            song_duration: Some(info.audio.songDuration),
+            audio_data_filename: Some(info.audio.audioDataFilename),
+            lufs: Some(info.audio.lufs),
+            song_preview_filename: Some(info.songPreviewFilename),
+            color_schemes: info.colorSchemes.into_iter().map(ColorScheme::from).collect(),
+            custom_data: info.customData,
+            difficulty_sets,
+            extras: info.extras,
+        }
+    }
+}
+
+impl BeatmapSetMeta {
+    /// Converts the info into a v4 `Info.dat`. v4 needs an audio data file, the song duration, a lightshow file for every difficulty and a color scheme for every
+    /// difficulty once there are any, which older infos don't have: they're left empty, or the first scheme is used, and reported
+    pub fn to_v4_info(&self) -> (schema::V4Info, Vec<ConversionWarning>) {
+        let mut warnings = vec![];
+        if self.audio_data_filename.is_none() {
+            warnings.push(ConversionWarning::AudioDataMissing);
+        }
+        if self.song_duration.is_none() {
+            warnings.push(ConversionWarning::SongDurationMissing);
+        }
+        let mut interner = Interner::default();
+        let mut environment_names = vec![];
+        interner.intern("environmentNames", &mut environment_names, self.environment_name.clone());
+        let mut difficulty_beatmaps = vec![];
+        for set in &self.difficulty_sets {
+            for beatmap in &set.beatmaps {
+                let default_environment_name = match &self.all_directions_environment_name {
+                    Some(name) if is_all_directions(&set.game_mode) => name,
+                    _ => &self.environment_name,
+                };
+                let environment_name = beatmap.environment_name.as_ref().unwrap_or(default_environment_name);
+                if beatmap.lightshow_filename.is_none() {
+                    warnings.push(ConversionWarning::LightshowFilenameMissing { filename: beatmap.filename.clone() });
+                }
+                if beatmap.color_scheme.is_none() && !self.color_schemes.is_empty() {
+                    warnings.push(ConversionWarning::ColorSchemeDefaulted { filename: beatmap.filename.clone() });
+                }
+                let mappers = if beatmap.mappers.is_empty() && !self.map_author.is_empty() { vec![self.map_author.clone()] } else { beatmap.mappers.clone() };
+                difficulty_beatmaps.push(schema::V4InfoDifficultyBeatmap {
+                    characteristic: set.game_mode.clone(),
+                    difficulty: beatmap.difficulty,
+                    beatmapAuthors: schema::V4BeatmapAuthors { mappers, lighters: beatmap.lighters.clone(), extras: HashMap::new() },
+                    environmentNameIdx: interner.intern("environmentNames", &mut environment_names, environment_name.clone()),
+                    beatmapColorSchemeIdx: beatmap.color_scheme.unwrap_or(0),
+                    noteJumpMovementSpeed: beatmap.note_jump_speed,
+                    noteJumpStartBeatOffset: beatmap.note_jump_start_beat_offset,
+                    beatmapDataFilename: beatmap.filename.clone(),
+                    lightshowDataFilename: beatmap.lightshow_filename.clone().unwrap_or_default(),
+                    customData: songcore_keys_to_version(&beatmap.custom_data, true),
+                    extras: beatmap.extras.clone(),
+                });
+            }
+        }
+
+        let info = schema::V4Info {
+            version: if self.version.starts_with('4') { self.version.clone() } else { "4.0.1".to_string() },
+            song: schema::V4InfoSong {
+                title: self.song_name.clone(),
+                subTitle: self.song_subname.clone(),
+                author: self.song_author.clone(),
+                extras: HashMap::new(),
+            },
+            audio: schema::V4InfoAudio {
+                songFilename: self.song_filename.clone(),
+                songDuration: self.song_duration.unwrap_or(0.0),
+                audioDataFilename: self.audio_data_filename.clone().unwrap_or_default(),
+                bpm: self.bpm,
+                lufs: self.lufs.unwrap_or(0.0),
+                previewStartTime: self.preview_start,
+                previewDuration: self.preview_duration,
+                extras: HashMap::new(),
+            },
+            songPreviewFilename: self.song_preview_filename.clone().unwrap_or_else(|| self.song_filename.clone()),
+            coverImageFilename: self.cover_image_filename.clone(),
+            environmentNames: environment_names,
+            colorSchemes: self.color_schemes.iter().map(schema::V4ColorScheme::from).collect(),
+            difficultyBeatmaps: difficulty_beatmaps,
+            customData: songcore_keys_to_version(&self.custom_data, true),
+            extras: self.extras.clone(),
+        };
+        (info, warnings)
+    }
+
+    /// Serializes the info in the v4 format, listing what it's missing
+    pub fn write_to_string_v4(&self) -> Result<(String, Vec<ConversionWarning>)> {
+        let (info, warnings) = self.to_v4_info();
+        Ok((info.write_to_string()?, warnings))
+    }
+
+    pub fn write_to_file_v4(&self, path: impl AsRef<Path>) -> Result<Vec<ConversionWarning>> {
+        let (data, warnings) = self.write_to_string_v4()?;
+        write_string_to_file(path, &data)?;
+        Ok(warnings)
+    }
+}
+
+/// 90 and 360 degree sets use the info's all directions environment
+fn is_all_directions(characteristic: &str) -> bool {
+    characteristic == "90Degree" || characteristic == "360Degree"
+}
+
+/// Parses `RRGGBB` or `RRGGBBAA`
+fn color_from_hex(hex: &str) -> Option<[f64; 4]> {
+    let channel = |i: usize| u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok().map(|x| x as f64 / 255.0);
+    match hex.len() {
+        6 => Some([channel(0)?, channel(1)?, channel(2)?, 1.0]),
+        8 => Some([channel(0)?, channel(1)?, channel(2)?, channel(3)?]),
+        _ => None,
+    }
+}
+
+fn color_to_hex(color: [f64; 4]) -> String {
+    color.iter().map(|x| format!("{:02X}", (x.clamp(0.0, 1.0) * 255.0).round() as u8)).collect()
+}
+
+impl From<schema::V4ColorScheme> for ColorScheme {
+    fn from(scheme: schema::V4ColorScheme) -> Self {
+        let default = ColorScheme::default();
+        let color = |hex: &str, fallback: [f64; 4]| color_from_hex(hex).unwrap_or(fallback);
+        Self {
+            name: scheme.colorSchemeName,
+            override_notes: scheme.overrideNotes,
+            saber_left: color(&scheme.saberAColor, default.saber_left),
+            saber_right: color(&scheme.saberBColor, default.saber_right),
+            obstacle: color(&scheme.obstaclesColor, default.obstacle),
+            override_lights: scheme.overrideLights,
+            env_left: color(&scheme.environmentColor0, default.env_left),
+            env_right: color(&scheme.environmentColor1, default.env_right),
+            env_white: color_from_hex(&scheme.environmentColorW),
+            env_left_boost: color(&scheme.environmentColor0Boost, default.env_left_boost),
+            env_right_boost: color(&scheme.environmentColor1Boost, default.env_right_boost),
+            env_white_boost: color_from_hex(&scheme.environmentColorWBoost),
+        }
+    }
+}
+
+impl From<&ColorScheme> for schema::V4ColorScheme {
+    fn from(scheme: &ColorScheme) -> Self {
+        Self {
+            colorSchemeName: scheme.name.clone(),
+            overrideNotes: scheme.override_notes,
+            saberAColor: color_to_hex(scheme.saber_left),
+            saberBColor: color_to_hex(scheme.saber_right),
+            obstaclesColor: color_to_hex(scheme.obstacle),
+            overrideLights: scheme.override_lights,
+            environmentColor0: color_to_hex(scheme.env_left),
+            environmentColor1: color_to_hex(scheme.env_right),
+            environmentColorW: scheme.env_white.map(color_to_hex).unwrap_or_default(),
+            environmentColor0Boost: color_to_hex(scheme.env_left_boost),
+            environmentColor1Boost: color_to_hex(scheme.env_right_boost),
+            environmentColorWBoost: scheme.env_white_boost.map(color_to_hex).unwrap_or_default(),
+            extras: HashMap::new(),
+        }
+    }
+}
+
+impl AudioData {
+    pub fn read_from_str(data: &str) -> Result<Self> {
+        Ok(schema::AudioDataFile::read_from_str(data)?.into())
+    }
+
+    pub fn read_from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::read_from_str(&read_string_from_file(path)?)
+    }
+
+    /// Serializes the data as `AudioData.dat` (v4)
+    pub fn write_to_string(&self) -> Result<String> {
+        schema::AudioDataFile::V4(Box::new(self.into())).write_to_string()
+    }
+
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        write_string_to_file(path, &self.write_to_string()?)
+    }
+
+    /// Serializes the data as `BPMInfo.dat` (v2), dropping the checksum and LUFS data
+    pub fn write_to_string_v2(&self) -> Result<String> {
+        schema::AudioDataFile::Old(Box::new(self.into())).write_to_string()
+    }
+
+    pub fn write_to_file_v2(&self, path: impl AsRef<Path>) -> Result<()> {
+        write_string_to_file(path, &self.write_to_string_v2()?)
+    }
+
+    pub fn sample_to_seconds(&self, sample: u64) -> f64 {
+        sample as f64 / self.frequency as f64
+    }
+}
+
+impl From<schema::AudioDataFile> for AudioData {
+    fn from(file: schema::AudioDataFile) -> Self {
+        match file {
+            schema::AudioDataFile::Old(data) => Self {
+                version: data._version,
+                checksum: None,
+                sample_count: data._songSampleCount,
+                frequency: data._songFrequency,
+                bpm_regions: data._regions.into_iter().map(|x| SampleRegion {
+                    start_sample: x._startSampleIndex,
+                    end_sample: x._endSampleIndex,
+                    start_beat: x._startBeat,
+                    end_beat: x._endBeat,
+                }).collect(),
+                lufs_regions: vec![],
+            },
+            schema::AudioDataFile::V4(data) => Self {
+                version: data.version,
+                checksum: Some(data.songChecksum).filter(|x| !x.is_empty()),
+                sample_count: data.songSampleCount,
+                frequency: data.songFrequency,
+                bpm_regions: data.bpmData.into_iter().map(|x| SampleRegion {
+                    start_sample: x.si,
+                    end_sample: x.ei,
+                    start_beat: x.sb,
+                    end_beat: x.eb,
+                }).collect(),
+                lufs_regions: data.lufsData.into_iter().map(|x| LufsRegion {
+                    start_sample: x.si,
+                    end_sample: x.ei,
+                    lufs: x.l,
+                }).collect(),
+            },
+        }
+    }
+}
+
+impl From<&AudioData> for schema::V4AudioData {
+    fn from(data: &AudioData) -> Self {
+        Self {
+            version: if data.version.starts_with('4') { data.version.clone() } else { "4.0.0".to_string() },
+            songChecksum: data.checksum.clone().unwrap_or_default(),
+            songSampleCount: data.sample_count,
+            songFrequency: data.frequency,
+            bpmData: data.bpm_regions.iter().map(|x| schema::V4BpmData {
+                si: x.start_sample,
+                ei: x.end_sample,
+                sb: x.start_beat,
+                eb: x.end_beat,
+                extras: HashMap::new(),
+            }).collect(),
+            lufsData: data.lufs_regions.iter().map(|x| schema::V4LufsData {
+                si: x.start_sample,
+                ei: x.end_sample,
+                l: x.lufs,
+                extras: HashMap::new(),
+            }).collect(),
+            extras: HashMap::new(),
+        }
+    }
+}
+
+impl From<&AudioData> for schema::OldAudioData {
+    fn from(data: &AudioData) -> Self {
+        Self {
+            _version: if data.version.starts_with('2') { data.version.clone() } else { "2.0.0".to_string() },
+            _songSampleCount: data.sample_count,
+            _songFrequency: data.frequency,
+            _regions: data.bpm_regions.iter().map(|x| schema::OldBpmRegion {
+                _startSampleIndex: x.start_sample,
+                _endSampleIndex: x.end_sample,
+                _startBeat: x.start_beat,
+                _endBeat: x.end_beat,
+                extras: HashMap::new(),
+            }).collect(),
+            extras: HashMap::new(),
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+use serde_json::{json, Value};
+use crate::types::primary::{BasicEvent, BeatmapMeta, BeatmapSetMeta, Bomb, BurstSlider, Note, Obstacle, Slider};
+
+/// Objects that carry `customData` (`_customData` in v2)
+pub trait HasCustomData {
+    fn custom_data(&self) -> &HashMap<String, Value>;
+    fn custom_data_mut(&mut self) -> &mut HashMap<String, Value>;
+
+    /// Looks a key up by its v3 name, falling back to its v2 name
+    fn custom_value(&self, key: &str, old_key: &str) -> Option<&Value> {
+        self.custom_data().get(key).or_else(|| self.custom_data().get(old_key))
+    }
+
+    /// Whether the custom data uses the v2 dialect, i.e. `_`-prefixed keys
+    fn uses_v2_keys(&self) -> bool {
+        self.custom_data().keys().any(|x| x.starts_with('_'))
+    }
+
+    /// Sets a key in the dialect the custom data already uses, v3 when there's nothing to go by, and removes the other name. `None` removes the key
+    fn set_custom_value(&mut self, key: &str, old_key: &str, value: Option<Value>) {
+        let uses_v2_keys = self.uses_v2_keys();
+        let custom_data = self.custom_data_mut();
+        custom_data.remove(key);
+        custom_data.remove(old_key);
+        match value {
+            Some(value) if uses_v2_keys => {
+                let negated = KEY_NAMES.iter().any(|(name, _, negated)| *name == key && *negated);
+                custom_data.insert(old_key.to_string(), if negated { negate(value) } else { value });
+            },
+            Some(value) => {
+                custom_data.insert(key.to_string(), value);
+            },
+            None => {},
+        }
+    }
+}
+
+macro_rules! impl_has_custom_data {
+    ($($t:ty),*) => {
+        $(impl HasCustomData for $t {
+            fn custom_data(&self) -> &HashMap<String, Value> {
+                &self.custom_data
+            }
+
+            fn custom_data_mut(&mut self) -> &mut HashMap<String, Value> {
+                &mut self.custom_data
+            }
+        })*
+    };
+}
+
+impl_has_custom_data!(Note, Bomb, Obstacle, Slider, BurstSlider, BasicEvent, BeatmapMeta, BeatmapSetMeta);
+
+fn as_floats<const N: usize>(value: &Value) -> Option<[f64; N]> {
+    let array = value.as_array()?;
+    if array.len() != N {
+        return None;
+    }
+    let mut result = [0.0; N];
+    for (result, value) in result.iter_mut().zip(array) {
+        *result = value.as_f64()?;
+    }
+    Some(result)
+}
+
+/// Object keys of Noodle Extensions and Chroma by their v3 and v2 names. Marked keys store the opposite value in v2
+pub(crate) const KEY_NAMES: [(&str, &str, bool); 22] = [
+    ("coordinates", "_position", false),
+    ("worldRotation", "_rotation", false),
+    ("localRotation", "_localRotation", false),
+    ("size", "_scale", false),
+    ("noteJumpMovementSpeed", "_noteJumpMovementSpeed", false),
+    ("noteJumpStartBeatOffset", "_noteJumpStartBeatOffset", false),
+    ("uninteractable", "_interactable", true),
+    ("flip", "_flip", false),
+    ("disableNoteGravity", "_disableNoteGravity", false),
+    ("disableNoteLook", "_disableNoteLook", false),
+    ("track", "_track", false),
+    ("animation", "_animation", false),
+    ("color", "_color", false),
+    ("spawnEffect", "_disableSpawnEffect", true),
+    ("disableDebris", "_disableDebris", false),
+    ("lightID", "_lightID", false),
+    ("easing", "_easing", false),
+    ("lerpType", "_lerpType", false),
+    ("lockRotation", "_lockPosition", false),
+    ("direction", "_direction", false),
+    ("nameFilter", "_nameFilter", false),
+    ("rotation", "_rotation", false),
+];
+
+/// Properties of `animation` and of Heck's track animations by their v3 and v2 names
+pub(crate) const ANIMATION_NAMES: [(&str, &str); 10] = [
+    ("offsetPosition", "_position"),
+    ("offsetWorldRotation", "_rotation"),
+    ("localRotation", "_localRotation"),
+    ("scale", "_scale"),
+    ("dissolve", "_dissolve"),
+    ("dissolveArrow", "_dissolveArrow"),
+    ("interactable", "_interactable"),
+    ("definitePosition", "_definitePosition"),
+    ("time", "_time"),
+    ("color", "_color"),
+];
+
+fn negate(value: Value) -> Value {
+    value.as_bool().map(|x| json!(!x)).unwrap_or(value)
+}
+
+/// Renames the v3 keys of an object's custom data to their v2 names. The second value lists the keys with no v2 name, which are kept as they are
+pub(crate) fn custom_data_to_v2(data: &HashMap<String, Value>) -> (HashMap<String, Value>, Vec<String>) {
+    let mut kept = vec![];
+    let mut result = HashMap::new();
+    for (key, value) in data {
+        let Some((_, old_key, negated)) = KEY_NAMES.iter().find(|(name, ..)| name == key) else {
+            if !key.starts_with('_') {
+                kept.push(key.clone());
+            }
+            result.insert(key.clone(), value.clone());
+            continue;
+        };
+        let value = match value {
+            Value::Object(properties) if *old_key == "_animation" => Value::Object(properties.iter().map(|(property, value)| {
+                match ANIMATION_NAMES.iter().find(|(name, _)| name == property) {
+                    Some((_, old_property)) => (old_property.to_string(), value.clone()),
+                    None => {
+                        kept.push(format!("animation.{property}"));
+                        (property.clone(), value.clone())
+                    },
+                }
+            }).collect()),
+            value if *negated => negate(value.clone()),
+            value => value.clone(),
+        };
+        result.insert(old_key.to_string(), value);
+    }
+    kept.sort();
+    (result, kept)
+}
+
+/// Renames the v2 keys of an object's custom data to their v3 names, the inverse of [custom_data_to_v2]. The second value lists the keys with no v3 name, which are kept as they are
+pub(crate) fn custom_data_from_v2(data: &HashMap<String, Value>, is_event: bool) -> (HashMap<String, Value>, Vec<String>) {
+    let mut kept = vec![];
+    let mut result = HashMap::new();
+    for (key, value) in data {
+        // `_rotation` is `worldRotation` on objects, but the ring `rotation` on events, which is listed last
+        let mut names = KEY_NAMES.iter().filter(|(_, old_key, _)| old_key == key);
+        let Some((new_key, _, negated)) = (if is_event { names.next_back() } else { names.next() }) else {
+            if key.starts_with('_') {
+                kept.push(key.clone());
+            }
+            result.insert(key.clone(), value.clone());
+            continue;
+        };
+        let value = match value {
+            Value::Object(properties) if *new_key == "animation" => Value::Object(properties.iter().map(|(property, value)| {
+                match ANIMATION_NAMES.iter().find(|(_, old_property)| old_property == property) {
+                    Some((new_property, _)) => (new_property.to_string(), value.clone()),
+                    None => {
+                        kept.push(format!("_animation.{property}"));
+                        (property.clone(), value.clone())
+                    },
+                }
+            }).collect()),
+            value if *negated => negate(value.clone()),
+            value => value.clone(),
+        };
+        result.insert(new_key.to_string(), value);
+    }
+    kept.sort();
+    (result, kept)
+}
+
+pub(crate) fn as_strings(value: &Value) -> Option<Vec<String>> {
+    match value {
+        Value::String(value) => Some(vec![value.clone()]),
+        Value::Array(values) => values.iter().map(|x| x.as_str().map(String::from)).collect(),
+        _ => None
+    }
+}
+
+/// Typed access to the common [Noodle Extensions](https://github.com/Aeroluna/Heck/wiki) keys
+pub trait NoodleData: HasCustomData {
+    fn coordinates(&self) -> Option<[f64; 2]> {
+        as_floats(self.custom_value("coordinates", "_position")?)
+    }
+
+    fn set_coordinates(&mut self, value: Option<[f64; 2]>) {
+        self.set_custom_value("coordinates", "_position", value.map(|x| json!(x)));
+    }
+
+    /// A single number is a rotation around the Y axis
+    fn world_rotation(&self) -> Option<[f64; 3]> {
+        let value = self.custom_value("worldRotation", "_rotation")?;
+        value.as_f64().map(|y| [0.0, y, 0.0]).or_else(|| as_floats(value))
+    }
+
+    fn set_world_rotation(&mut self, value: Option<[f64; 3]>) {
+        self.set_custom_value("worldRotation", "_rotation", value.map(|x| json!(x)));
+    }
+
+    fn local_rotation(&self) -> Option<[f64; 3]> {
+        as_floats(self.custom_value("localRotation", "_localRotation")?)
+    }
+
+    fn set_local_rotation(&mut self, value: Option<[f64; 3]>) {
+        self.set_custom_value("localRotation", "_localRotation", value.map(|x| json!(x)));
+    }
+
+    /// Obstacle size as `[width, height, length]`. Missing components are left out
+    fn size(&self) -> Option<Vec<f64>> {
+        self.custom_value("size", "_scale")?.as_array()?.iter().map(|x| x.as_f64()).collect()
+    }
+
+    fn set_size(&mut self, value: Option<Vec<f64>>) {
+        self.set_custom_value("size", "_scale", value.map(|x| json!(x)));
+    }
+
+    fn note_jump_speed(&self) -> Option<f64> {
+        self.custom_value("noteJumpMovementSpeed", "_noteJumpMovementSpeed")?.as_f64()
+    }
+
+    fn set_note_jump_speed(&mut self, value: Option<f64>) {
+        self.set_custom_value("noteJumpMovementSpeed", "_noteJumpMovementSpeed", value.map(|x| json!(x)));
+    }
+
+    fn note_jump_start_beat_offset(&self) -> Option<f64> {
+        self.custom_value("noteJumpStartBeatOffset", "_noteJumpStartBeatOffset")?.as_f64()
+    }
+
+    fn set_note_jump_start_beat_offset(&mut self, value: Option<f64>) {
+        self.set_custom_value("noteJumpStartBeatOffset", "_noteJumpStartBeatOffset", value.map(|x| json!(x)));
+    }
+
+    /// v2 stores the opposite, `_interactable`
+    fn uninteractable(&self) -> Option<bool> {
+        self.custom_data().get("uninteractable")
+            .and_then(|x| x.as_bool())
+            .or_else(|| self.custom_data().get("_interactable")?.as_bool().map(|x| !x))
+    }
+
+    fn set_uninteractable(&mut self, value: Option<bool>) {
+        self.set_custom_value("uninteractable", "_interactable", value.map(|x| json!(x)));
+    }
+
+    fn flip(&self) -> Option<[f64; 2]> {
+        as_floats(self.custom_value("flip", "_flip")?)
+    }
+
+    fn set_flip(&mut self, value: Option<[f64; 2]>) {
+        self.set_custom_value("flip", "_flip", value.map(|x| json!(x)));
+    }
+
+    fn disable_note_gravity(&self) -> Option<bool> {
+        self.custom_value("disableNoteGravity", "_disableNoteGravity")?.as_bool()
+    }
+
+    fn set_disable_note_gravity(&mut self, value: Option<bool>) {
+        self.set_custom_value("disableNoteGravity", "_disableNoteGravity", value.map(|x| json!(x)));
+    }
+
+    fn disable_note_look(&self) -> Option<bool> {
+        self.custom_value("disableNoteLook", "_disableNoteLook")?.as_bool()
+    }
+
+    fn set_disable_note_look(&mut self, value: Option<bool>) {
+        self.set_custom_value("disableNoteLook", "_disableNoteLook", value.map(|x| json!(x)));
+    }
+
+    /// Track names. A single track is stored as a plain string
+    fn track(&self) -> Option<Vec<String>> {
+        as_strings(self.custom_value("track", "_track")?)
+    }
+
+    fn set_track(&mut self, value: Option<Vec<String>>) {
+        let value = value.map(|mut x| if x.len() == 1 { json!(x.remove(0)) } else { json!(x) });
+        self.set_custom_value("track", "_track", value);
+    }
+
+    /// Raw `animation` object, keyed by property
+    fn animation(&self) -> Option<&serde_json::Map<String, Value>> {
+        self.custom_value("animation", "_animation")?.as_object()
+    }
+
+    fn set_animation(&mut self, value: Option<serde_json::Map<String, Value>>) {
+        self.set_custom_value("animation", "_animation", value.map(Value::Object));
+    }
+}
+
+/// Typed access to the common [Chroma](https://github.com/Aeroluna/Heck/wiki) keys
+pub trait ChromaData: HasCustomData {
+    /// RGBA, alpha defaults to 1
+    fn color(&self) -> Option<[f64; 4]> {
+        let value = self.custom_value("color", "_color")?;
+        as_floats::<4>(value).or_else(|| as_floats::<3>(value).map(|[r, g, b]| [r, g, b, 1.0]))
+    }
+
+    fn set_color(&mut self, value: Option<[f64; 4]>) {
+        self.set_custom_value("color", "_color", value.map(|x| json!(x)));
+    }
+
+    fn spawn_effect(&self) -> Option<bool> {
+        self.custom_data().get("spawnEffect")
+            .and_then(|x| x.as_bool())
+            .or_else(|| self.custom_data().get("_disableSpawnEffect")?.as_bool().map(|x| !x))
+    }
+
+    fn set_spawn_effect(&mut self, value: Option<bool>) {
+        self.set_custom_value("spawnEffect", "_disableSpawnEffect", value.map(|x| json!(x)));
+    }
+
+    fn disable_debris(&self) -> Option<bool> {
+        self.custom_value("disableDebris", "_disableDebris")?.as_bool()
+    }
+
+    fn set_disable_debris(&mut self, value: Option<bool>) {
+        self.set_custom_value("disableDebris", "_disableDebris", value.map(|x| json!(x)));
+    }
+}
+
+macro_rules! impl_object_data {
+    ($($t:ty),*) => {
+        $(impl NoodleData for $t {}
+        impl ChromaData for $t {})*
+    };
+}
+
+impl_object_data!(Note, Bomb, Obstacle, Slider, BurstSlider);
@@ -0,0 +1,548 @@
+use std::collections::HashMap;
+use serde_json::{json, Map, Value};
+use crate::error::{Error, Result};
+use crate::types::custom_data::ANIMATION_NAMES;
+use crate::Beatmap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HeckEasing {
+    Linear,
+    Step,
+    InQuad, OutQuad, InOutQuad,
+    InCubic, OutCubic, InOutCubic,
+    InQuart, OutQuart, InOutQuart,
+    InQuint, OutQuint, InOutQuint,
+    InSine, OutSine, InOutSine,
+    InExpo, OutExpo, InOutExpo,
+    InCirc, OutCirc, InOutCirc,
+    InBack, OutBack, InOutBack,
+    InElastic, OutElastic, InOutElastic,
+    InBounce, OutBounce, InOutBounce,
+}
+
+const EASING_NAMES: [(HeckEasing, &str); 32] = {
+    use HeckEasing::*;
+    [
+        (Linear, "easeLinear"), (Step, "easeStep"),
+        (InQuad, "easeInQuad"), (OutQuad, "easeOutQuad"), (InOutQuad, "easeInOutQuad"),
+        (InCubic, "easeInCubic"), (OutCubic, "easeOutCubic"), (InOutCubic, "easeInOutCubic"),
+        (InQuart, "easeInQuart"), (OutQuart, "easeOutQuart"), (InOutQuart, "easeInOutQuart"),
+        (InQuint, "easeInQuint"), (OutQuint, "easeOutQuint"), (InOutQuint, "easeInOutQuint"),
+        (InSine, "easeInSine"), (OutSine, "easeOutSine"), (InOutSine, "easeInOutSine"),
+        (InExpo, "easeInExpo"), (OutExpo, "easeOutExpo"), (InOutExpo, "easeInOutExpo"),
+        (InCirc, "easeInCirc"), (OutCirc, "easeOutCirc"), (InOutCirc, "easeInOutCirc"),
+        (InBack, "easeInBack"), (OutBack, "easeOutBack"), (InOutBack, "easeInOutBack"),
+        (InElastic, "easeInElastic"), (OutElastic, "easeOutElastic"), (InOutElastic, "easeInOutElastic"),
+        (InBounce, "easeInBounce"), (OutBounce, "easeOutBounce"), (InOutBounce, "easeInOutBounce"),
+    ]
+};
+
+impl HeckEasing {
+    pub fn from_name(name: &str) -> Option<Self> {
+        EASING_NAMES.iter().find(|(_, x)| *x == name).map(|(easing, _)| *easing)
+    }
+
+    pub fn name(&self) -> &'static str {
+        EASING_NAMES.iter().find(|(x, _)| x == self).unwrap().1
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    None,
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl Operation {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "opNone" => Self::None,
+            "opAdd" => Self::Add,
+            "opSub" => Self::Sub,
+            "opMul" => Self::Mul,
+            "opDiv" => Self::Div,
+            _ => return None
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::None => "opNone",
+            Self::Add => "opAdd",
+            Self::Sub => "opSub",
+            Self::Mul => "opMul",
+            Self::Div => "opDiv",
+        }
+    }
+}
+
+/// A component of a point: a number or a base game value such as `baseHeadPosition`
+#[derive(Debug, Clone, PartialEq)]
+pub enum PointValue {
+    Number(f64),
+    Base(String),
+}
+
+impl PointValue {
+    fn to_value(&self) -> Value {
+        match self {
+            Self::Number(value) => json!(value),
+            Self::Base(name) => json!(name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Modifier {
+    pub values: Vec<PointValue>,
+    pub modifiers: Vec<Modifier>,
+    pub operation: Operation,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Point {
+    /// Everything but the time, e.g. `[x, y, z]` for a position
+    pub values: Vec<PointValue>,
+    pub time: f64,
+    pub easing: Option<HeckEasing>,
+    /// `splineCatmullRom`
+    pub spline: bool,
+    /// `lerpHSV`, only meaningful for colors
+    pub hsv_lerp: bool,
+    pub modifiers: Vec<Modifier>,
+}
+
+impl Point {
+    pub fn new(values: Vec<f64>, time: f64) -> Self {
+        Self {
+            values: values.into_iter().map(PointValue::Number).collect(),
+            time,
+            easing: None,
+            spline: false,
+            hsv_lerp: false,
+            modifiers: vec![],
+        }
+    }
+
+    /// Numeric values of the point, if it doesn't depend on base game values
+    pub fn numbers(&self) -> Option<Vec<f64>> {
+        self.values.iter().map(|x| match x {
+            PointValue::Number(value) => Some(*value),
+            PointValue::Base(_) => None,
+        }).collect()
+    }
+
+    fn from_value(value: &Value, has_time: bool) -> Result<Self> {
+        let array = value.as_array().ok_or_else(|| invalid("point", value))?;
+        let mut point = Point { values: vec![], time: 0.0, easing: None, spline: false, hsv_lerp: false, modifiers: vec![] };
+        for item in array {
+            match item {
+                Value::Number(number) => point.values.push(PointValue::Number(number.as_f64().unwrap())),
+                Value::String(name) if name.starts_with("base") => point.values.push(PointValue::Base(name.clone())),
+                Value::String(name) if name == "splineCatmullRom" => point.spline = true,
+                Value::String(name) if name == "lerpHSV" => point.hsv_lerp = true,
+                Value::String(name) => point.easing = Some(HeckEasing::from_name(name).ok_or_else(|| invalid("easing", item))?),
+                Value::Array(_) => point.modifiers.push(Modifier::from_value(item)?),
+                _ => return Err(invalid("point", value))
+            }
+        }
+        if has_time {
+            match point.values.pop() {
+                Some(PointValue::Number(time)) => point.time = time,
+                _ => return Err(invalid("point", value))
+            }
+        }
+        Ok(point)
+    }
+
+    fn to_value(&self) -> Value {
+        let mut array: Vec<Value> = self.values.iter().map(PointValue::to_value).collect();
+        array.push(json!(self.time));
+        array.extend(self.modifiers.iter().map(Modifier::to_value));
+        if let Some(easing) = self.easing {
+            array.push(json!(easing.name()));
+        }
+        if self.spline {
+            array.push(json!("splineCatmullRom"));
+        }
+        if self.hsv_lerp {
+            array.push(json!("lerpHSV"));
+        }
+        Value::Array(array)
+    }
+}
+
+impl Modifier {
+    fn from_value(value: &Value) -> Result<Self> {
+        let array = value.as_array().ok_or_else(|| invalid("modifier", value))?;
+        let mut modifier = Modifier { values: vec![], modifiers: vec![], operation: Operation::None };
+        for item in array {
+            match item {
+                Value::Number(number) => modifier.values.push(PointValue::Number(number.as_f64().unwrap())),
+                Value::String(name) if name.starts_with("base") => modifier.values.push(PointValue::Base(name.clone())),
+                Value::String(name) => modifier.operation = Operation::from_name(name).ok_or_else(|| invalid("operation", item))?,
+                Value::Array(_) => modifier.modifiers.push(Modifier::from_value(item)?),
+                _ => return Err(invalid("modifier", value))
+            }
+        }
+        Ok(modifier)
+    }
+
+    fn to_value(&self) -> Value {
+        let mut array: Vec<Value> = self.values.iter().map(PointValue::to_value).collect();
+        array.extend(self.modifiers.iter().map(Modifier::to_value));
+        array.push(json!(self.operation.name()));
+        Value::Array(array)
+    }
+}
+
+/// Either inline points or the name of a definition from the beatmap's point definitions
+#[derive(Debug, Clone, PartialEq)]
+pub enum PointDefinition {
+    Named(String),
+    Points(Vec<Point>),
+}
+
+impl PointDefinition {
+    /// A flat array, like `[0, 1, 0]`, is a single point without a time
+    pub fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::String(name) => Ok(Self::Named(name.clone())),
+            Value::Array(array) if array.first().map(Value::is_array).unwrap_or(false) =>
+                Ok(Self::Points(array.iter().map(|x| Point::from_value(x, true)).collect::<Result<_>>()?)),
+            Value::Array(_) => Ok(Self::Points(vec![Point::from_value(value, false)?])),
+            _ => Err(invalid("point definition", value))
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        match self {
+            Self::Named(name) => json!(name),
+            Self::Points(points) => Value::Array(points.iter().map(Point::to_value).collect()),
+        }
+    }
+
+    /// Looks a named definition up in `definitions`
+    pub fn resolve<'a>(&'a self, definitions: &'a HashMap<String, Vec<Point>>) -> Option<&'a [Point]> {
+        match self {
+            Self::Named(name) => definitions.get(name).map(Vec::as_slice),
+            Self::Points(points) => Some(points),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimateTrack {
+    pub track: Vec<String>,
+    pub duration: f64,
+    pub easing: Option<HeckEasing>,
+    pub repeat: Option<i32>,
+    /// Keyed by v3 property name, e.g. `offsetPosition` or `dissolve`. v2 names are translated
+    pub properties: HashMap<String, PointDefinition>,
+    pub extras: HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssignPathAnimation {
+    pub track: Vec<String>,
+    pub duration: f64,
+    pub easing: Option<HeckEasing>,
+    pub properties: HashMap<String, PointDefinition>,
+    pub extras: HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssignTrackParent {
+    pub children_tracks: Vec<String>,
+    pub parent_track: String,
+    pub world_position_stays: Option<bool>,
+    pub extras: HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssignPlayerToTrack {
+    pub track: String,
+    pub player_track_object: Option<String>,
+    pub extras: HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssignFogTrack {
+    pub track: String,
+    pub extras: HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CustomEventKind {
+    AnimateTrack(AnimateTrack),
+    AssignPathAnimation(AssignPathAnimation),
+    AssignTrackParent(AssignTrackParent),
+    AssignPlayerToTrack(AssignPlayerToTrack),
+    AssignFogTrack(AssignFogTrack),
+    Other { kind: String, data: HashMap<String, Value> },
+}
+
+/// A Heck custom event, from `customEvents` (`_customEvents` in v2)
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomEvent {
+    pub beat: f64,
+    pub kind: CustomEventKind,
+}
+
+fn invalid(what: &str, value: &Value) -> Error {
+    Error::CustomDataParsingFailed(format!("invalid {what}: {value}"))
+}
+
+/// Event data with the v2 underscores stripped from its keys
+struct EventData(HashMap<String, Value>);
+
+impl EventData {
+    fn new(data: &Map<String, Value>, is_v2: bool) -> Self {
+        Self(data.iter().map(|(key, value)| {
+            let key = if is_v2 { key.strip_prefix('_').unwrap_or(key) } else { key };
+            (key.to_string(), value.clone())
+        }).collect())
+    }
+
+    fn take(&mut self, key: &str) -> Option<Value> {
+        self.0.remove(key)
+    }
+
+    fn take_string(&mut self, key: &str) -> Result<String> {
+        let value = self.take(key).ok_or_else(|| Error::CustomDataParsingFailed(format!("missing {key}")))?;
+        value.as_str().map(String::from).ok_or_else(|| invalid(key, &value))
+    }
+
+    fn take_strings(&mut self, key: &str) -> Result<Vec<String>> {
+        match self.take(key) {
+            Some(Value::String(value)) => Ok(vec![value]),
+            Some(value) => value.as_array()
+                .and_then(|x| x.iter().map(|x| x.as_str().map(String::from)).collect())
+                .ok_or_else(|| invalid(key, &value)),
+            None => Err(Error::CustomDataParsingFailed(format!("missing {key}")))
+        }
+    }
+
+    fn take_easing(&mut self) -> Result<Option<HeckEasing>> {
+        self.take("easing").map(|x| x.as_str().and_then(HeckEasing::from_name).ok_or_else(|| invalid("easing", &x))).transpose()
+    }
+
+    /// Whatever parses as a point definition is a property, the rest stays as is. v2 property names are translated to v3
+    fn take_properties(&mut self, is_v2: bool) -> (HashMap<String, PointDefinition>, HashMap<String, Value>) {
+        let mut properties = HashMap::new();
+        let mut extras = HashMap::new();
+        for (key, value) in self.0.drain() {
+            match PointDefinition::from_value(&value) {
+                Ok(definition) => { properties.insert(if is_v2 { property_from_v2(&key) } else { key }, definition); },
+                Err(_) => { extras.insert(key, value); },
+            }
+        }
+        (properties, extras)
+    }
+}
+
+/// v3 name of a v2 property, both without the v2 underscore. Properties with no v3 name keep theirs
+fn property_from_v2(property: &str) -> String {
+    ANIMATION_NAMES.iter()
+        .find(|(_, old_property)| old_property.strip_prefix('_') == Some(property))
+        .map(|(name, _)| name.to_string())
+        .unwrap_or_else(|| property.to_string())
+}
+
+/// v2 name of a v3 property without the v2 underscore, if it has one
+fn property_to_v2(property: &str) -> Option<&'static str> {
+    ANIMATION_NAMES.iter().find(|(name, _)| *name == property).map(|(_, old_property)| &old_property[1..])
+}
+
+fn tracks_to_value(tracks: &[String]) -> Value {
+    if tracks.len() == 1 { json!(tracks[0]) } else { json!(tracks) }
+}
+
+impl CustomEvent {
+    pub fn from_value(value: &Value, is_v2: bool) -> Result<Self> {
+        let (beat_key, kind_key, data_key) = if is_v2 { ("_time", "_type", "_data") } else { ("b", "t", "d") };
+        let beat = value.get(beat_key).and_then(Value::as_f64).ok_or_else(|| invalid("custom event", value))?;
+        let kind = value.get(kind_key).and_then(Value::as_str).ok_or_else(|| invalid("custom event", value))?;
+        let empty = Map::new();
+        let raw_data = value.get(data_key).and_then(Value::as_object).unwrap_or(&empty);
+        let mut data = EventData::new(raw_data, is_v2);
+
+        let kind = match kind {
+            "AnimateTrack" => {
+                let track = data.take_strings("track")?;
+                let duration = data.take("duration").and_then(|x| x.as_f64()).unwrap_or(0.0);
+                let easing = data.take_easing()?;
+                let repeat = data.take("repeat").and_then(|x| x.as_i64()).map(|x| x as i32);
+                let (properties, extras) = data.take_properties(is_v2);
+                CustomEventKind::AnimateTrack(AnimateTrack { track, duration, easing, repeat, properties, extras })
+            },
+            "AssignPathAnimation" => {
+                let track = data.take_strings("track")?;
+                let duration = data.take("duration").and_then(|x| x.as_f64()).unwrap_or(0.0);
+                let easing = data.take_easing()?;
+                let (properties, extras) = data.take_properties(is_v2);
+                CustomEventKind::AssignPathAnimation(AssignPathAnimation { track, duration, easing, properties, extras })
+            },
+            "AssignTrackParent" => CustomEventKind::AssignTrackParent(AssignTrackParent {
+                children_tracks: data.take_strings("childrenTracks")?,
+                parent_track: data.take_string("parentTrack")?,
+                world_position_stays: data.take("worldPositionStays").and_then(|x| x.as_bool()),
+                extras: data.0,
+            }),
+            "AssignPlayerToTrack" => CustomEventKind::AssignPlayerToTrack(AssignPlayerToTrack {
+                track: data.take_string("track")?,
+                player_track_object: data.take("playerTrackObject").and_then(|x| x.as_str().map(String::from)),
+                extras: data.0,
+            }),
+            "AssignFogTrack" => CustomEventKind::AssignFogTrack(AssignFogTrack {
+                track: data.take_string("track")?,
+                extras: data.0,
+            }),
+            kind => CustomEventKind::Other {
+                kind: kind.to_string(),
+                data: raw_data.iter().map(|(key, value)| (key.clone(), value.clone())).collect(),
+            }
+        };
+        Ok(Self { beat, kind })
+    }
+
+    /// Animated properties with no v2 name, which only get an underscore in v2
+    pub fn properties_without_v2_name(&self) -> Vec<&str> {
+        let properties = match &self.kind {
+            CustomEventKind::AnimateTrack(event) => &event.properties,
+            CustomEventKind::AssignPathAnimation(event) => &event.properties,
+            _ => return vec![],
+        };
+        let mut result: Vec<&str> = properties.keys().map(String::as_str).filter(|x| property_to_v2(x).is_none()).collect();
+        result.sort();
+        result
+    }
+
+    pub fn to_value(&self, is_v2: bool) -> Value {
+        let mut data = Map::new();
+        let mut insert = |key: &str, value: Value| {
+            data.insert(if is_v2 { format!("_{key}") } else { key.to_string() }, value);
+        };
+        let kind = match &self.kind {
+            CustomEventKind::AnimateTrack(event) => {
+                insert("track", tracks_to_value(&event.track));
+                insert("duration", json!(event.duration));
+                if let Some(easing) = event.easing {
+                    insert("easing", json!(easing.name()));
+                }
+                if let Some(repeat) = event.repeat {
+                    insert("repeat", json!(repeat));
+                }
+                event.properties.iter().for_each(|(key, value)| insert(if is_v2 { property_to_v2(key).unwrap_or(key) } else { key }, value.to_value()));
+                event.extras.iter().for_each(|(key, value)| insert(key, value.clone()));
+                "AnimateTrack"
+            },
+            CustomEventKind::AssignPathAnimation(event) => {
+                insert("track", tracks_to_value(&event.track));
+                insert("duration", json!(event.duration));
+                if let Some(easing) = event.easing {
+                    insert("easing", json!(easing.name()));
+                }
+                event.properties.iter().for_each(|(key, value)| insert(if is_v2 { property_to_v2(key).unwrap_or(key) } else { key }, value.to_value()));
+                event.extras.iter().for_each(|(key, value)| insert(key, value.clone()));
+                "AssignPathAnimation"
+            },
+            CustomEventKind::AssignTrackParent(event) => {
+                insert("childrenTracks", json!(event.children_tracks));
+                insert("parentTrack", json!(event.parent_track));
+                if let Some(world_position_stays) = event.world_position_stays {
+                    insert("worldPositionStays", json!(world_position_stays));
+                }
+                event.extras.iter().for_each(|(key, value)| insert(key, value.clone()));
+                "AssignTrackParent"
+            },
+            CustomEventKind::AssignPlayerToTrack(event) => {
+                insert("track", json!(event.track));
+                if let Some(object) = &event.player_track_object {
+                    insert("playerTrackObject", json!(object));
+                }
+                event.extras.iter().for_each(|(key, value)| insert(key, value.clone()));
+                "AssignPlayerToTrack"
+            },
+            CustomEventKind::AssignFogTrack(event) => {
+                insert("track", json!(event.track));
+                event.extras.iter().for_each(|(key, value)| insert(key, value.clone()));
+                "AssignFogTrack"
+            },
+            CustomEventKind::Other { kind, data: raw_data } => {
+                data = raw_data.iter().map(|(key, value)| (key.clone(), value.clone())).collect();
+                kind
+            },
+        };
+        if is_v2 {
+            json!({ "_time": self.beat, "_type": kind, "_data": data })
+        } else {
+            json!({ "b": self.beat, "t": kind, "d": data })
+        }
+    }
+}
+
+impl Beatmap {
+    fn is_v2(&self) -> bool {
+        self.version.starts_with('2')
+    }
+
+    /// Parses `customEvents` (`_customEvents` in v2) out of [Beatmap::custom_data]
+    pub fn custom_events(&self) -> Result<Vec<CustomEvent>> {
+        let is_v2 = self.is_v2();
+        let key = if is_v2 { "_customEvents" } else { "customEvents" };
+        match self.custom_data.get(key) {
+            Some(Value::Array(events)) => events.iter().map(|x| CustomEvent::from_value(x, is_v2)).collect(),
+            Some(value) => Err(invalid(key, value)),
+            None => Ok(vec![])
+        }
+    }
+
+    pub fn set_custom_events(&mut self, events: &[CustomEvent]) {
+        let is_v2 = self.is_v2();
+        let key = if is_v2 { "_customEvents" } else { "customEvents" };
+        if events.is_empty() {
+            self.custom_data.remove(key);
+        } else {
+            self.custom_data.insert(key.to_string(), events.iter().map(|x| x.to_value(is_v2)).collect());
+        }
+    }
+
+    /// Parses `pointDefinitions` (`_pointDefinitions` in v2) out of [Beatmap::custom_data]
+    pub fn point_definitions(&self) -> Result<HashMap<String, Vec<Point>>> {
+        let points = |value: &Value| match PointDefinition::from_value(value)? {
+            PointDefinition::Points(points) => Ok(points),
+            PointDefinition::Named(_) => Err(invalid("point definition", value)),
+        };
+        if self.is_v2() {
+            let Some(definitions) = self.custom_data.get("_pointDefinitions") else { return Ok(HashMap::new()) };
+            let definitions = definitions.as_array().ok_or_else(|| invalid("_pointDefinitions", definitions))?;
+            definitions.iter().map(|x| {
+                let name = x.get("_name").and_then(Value::as_str).ok_or_else(|| invalid("point definition", x))?;
+                let value = x.get("_points").ok_or_else(|| invalid("point definition", x))?;
+                Ok((name.to_string(), points(value)?))
+            }).collect()
+        } else {
+            let Some(definitions) = self.custom_data.get("pointDefinitions") else { return Ok(HashMap::new()) };
+            let definitions = definitions.as_object().ok_or_else(|| invalid("pointDefinitions", definitions))?;
+            definitions.iter().map(|(name, value)| Ok((name.clone(), points(value)?))).collect()
+        }
+    }
+
+    pub fn set_point_definitions(&mut self, definitions: &HashMap<String, Vec<Point>>) {
+        let to_value = |points: &Vec<Point>| Value::Array(points.iter().map(Point::to_value).collect());
+        let (key, value) = if self.is_v2() {
+            ("_pointDefinitions", definitions.iter().map(|(name, points)| json!({ "_name": name, "_points": to_value(points) })).collect())
+        } else {
+            ("pointDefinitions", Value::Object(definitions.iter().map(|(name, points)| (name.clone(), to_value(points))).collect()))
+        };
+        if definitions.is_empty() {
+            self.custom_data.remove(key);
+        } else {
+            self.custom_data.insert(key.to_string(), value);
+        }
+    }
+}
@@ -1,12 +1,13 @@
+use std::collections::HashMap;
 use crate::types::common::{BoxFilterOrdering, DistributionKind, Easing, LightColor, RotationBehaviour, RotationDirection, LimitKind, TransitionKind, Axis};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum BoxFilterSettings {
     Sections { count: i32, index: i32 },
     StepAndOffset { start: i32, skip: i32 },
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BoxFilter {
     pub chunks: i32,
     pub settings: BoxFilterSettings,
@@ -15,18 +16,20 @@ pub struct BoxFilter {
     pub random_seed: i32,
     pub limit: f64,
     pub limit_kind: LimitKind,
+    pub extras: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LightColorEvent {
     pub relative_beat: f64,
     pub transition_kind: TransitionKind,
     pub color: LightColor,
     pub brightness: f64,
     pub frequency: i32,
+    pub extras: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LightRotationEvent {
     pub relative_beat: f64,
     pub behaviour: RotationBehaviour,
@@ -34,24 +37,26 @@ pub struct LightRotationEvent {
     pub loops: i32,
     pub amount: f64,
     pub direction: RotationDirection,
+    pub extras: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LightTranslationEvent {
     pub relative_beat: f64,
     pub rotation_behaviour: RotationBehaviour,
     pub easing: Easing,
     pub amount: f64,
+    pub extras: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum LightEvents {
     Color(Vec<LightColorEvent>),
     Rotation(Vec<LightRotationEvent>),
     Translation(Vec<LightTranslationEvent>),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LightEventLane {
     pub filter: BoxFilter,
     pub beat_dist: f64,
@@ -63,11 +68,13 @@ pub struct LightEventLane {
     pub axis: Option<Axis>,
     pub reverse: Option<bool>,
     pub events: LightEvents,
+    pub extras: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LightEventBox {
     pub beat: f64,
     pub group: i32,
     pub lanes: Vec<LightEventLane>,
+    pub extras: HashMap<String, serde_json::Value>,
 }
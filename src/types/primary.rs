@@ -1,9 +1,9 @@
 use std::collections::HashMap;
-use crate::types::common::{Difficulty, Direction, NoteColor, SliderMidAnchorMode};
+use crate::types::common::{BasicEventType, Difficulty, Direction, LightColor, LightValue, NoteColor, SliderMidAnchorMode};
 use crate::types::lightning::LightEventBox;
 
 /// Holds info, contained in `info.dat`. That's the song info and the list of difficulties. Actual beatmaps are contained in [Beatmap]
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BeatmapSetMeta {
     pub version: String,
     pub song_name: String,
@@ -20,28 +20,134 @@ pub struct BeatmapSetMeta {
     pub environment_name: String,
     pub all_directions_environment_name: Option<String>,
     pub song_offset: f64,
+    /// v4 only, length of the song in seconds
+    pub song_duration: Option<f64>,
+    /// v4 only, the file holding BPM regions and loudness data
+    pub audio_data_filename: Option<String>,
+    /// v4 only, loudness of the song
+    pub lufs: Option<f64>,
+    /// v4 only, the audio played in the song selection menu
+    pub song_preview_filename: Option<String>,
+    /// v4 only, the schemes difficulties can pick from with [BeatmapMeta::color_scheme]
+    pub color_schemes: Vec<ColorScheme>,
     pub custom_data: HashMap<String, serde_json::Value>,
     pub difficulty_sets: Vec<DifficultySet>,
+    pub extras: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, PartialEq)]
+/// Contents of the audio data file (`AudioData.dat`, or `BPMInfo.dat` in older levels). When present, it decides the timing of the level
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioData {
+    pub version: String,
+    /// v4 only
+    pub checksum: Option<String>,
+    pub sample_count: u64,
+    /// Sample rate of the song, in Hz
+    pub frequency: u32,
+    pub bpm_regions: Vec<SampleRegion>,
+    /// v4 only
+    pub lufs_regions: Vec<LufsRegion>,
+}
+
+/// A span of the song with a constant BPM, given as sample indices and the beats they fall on
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleRegion {
+    pub start_sample: u64,
+    pub end_sample: u64,
+    pub start_beat: f64,
+    pub end_beat: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LufsRegion {
+    pub start_sample: u64,
+    pub end_sample: u64,
+    pub lufs: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct DifficultySet {
     pub game_mode: String,
     pub beatmaps: Vec<BeatmapMeta>,
+    pub extras: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BeatmapMeta {
     pub difficulty: Difficulty,
     pub rank: i32,
     pub filename: String,
     pub note_jump_speed: f64,
     pub note_jump_start_beat_offset: f64,
+    /// v4 only, the level as a whole is credited to [BeatmapSetMeta::map_author]
+    pub mappers: Vec<String>,
+    /// v4 only
+    pub lighters: Vec<String>,
+    /// v4 only, the environment this difficulty is played in
+    pub environment_name: Option<String>,
+    /// v4 only, index into [BeatmapSetMeta::color_schemes]
+    pub color_scheme: Option<usize>,
+    /// v4 only, the file holding the lights of this difficulty
+    pub lightshow_filename: Option<String>,
     pub custom_data: HashMap<String, serde_json::Value>,
+    pub extras: HashMap<String, serde_json::Value>,
+}
+
+/// Colors of a level, RGBA from 0 to 1. See [BeatmapSetMeta::color_scheme] for the colors a difficulty actually uses
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorScheme {
+    pub name: String,
+    /// Whether the saber and obstacle colors replace the environment's
+    pub override_notes: bool,
+    pub saber_left: [f64; 4],
+    pub saber_right: [f64; 4],
+    pub obstacle: [f64; 4],
+    /// Whether the light colors replace the environment's
+    pub override_lights: bool,
+    pub env_left: [f64; 4],
+    pub env_right: [f64; 4],
+    pub env_white: Option<[f64; 4]>,
+    pub env_left_boost: [f64; 4],
+    pub env_right_boost: [f64; 4],
+    pub env_white_boost: Option<[f64; 4]>,
+}
+
+/// The game's default colors (those of The First environment)
+impl Default for ColorScheme {
+    fn default() -> Self {
+        Self {
+            name: "Default".to_string(),
+            override_notes: false,
+            saber_left: [0.784, 0.078, 0.078, 1.0],
+            saber_right: [0.157, 0.557, 0.824, 1.0],
+            obstacle: [1.0, 0.188, 0.188, 1.0],
+            override_lights: false,
+            env_left: [0.85, 0.085, 0.085, 1.0],
+            env_right: [0.188, 0.675, 1.0, 1.0],
+            env_white: None,
+            env_left_boost: [0.85, 0.085, 0.085, 1.0],
+            env_right_boost: [0.188, 0.675, 1.0, 1.0],
+            env_white_boost: None,
+        }
+    }
+}
+
+impl ColorScheme {
+    /// Color of lights using `color`, from the boost palette if `boost` is set. White lights without a white color are plain white
+    pub fn light_color(&self, color: LightColor, boost: bool) -> [f64; 4] {
+        match (color, boost) {
+            (LightColor::Red, false) => self.env_left,
+            (LightColor::Blue, false) => self.env_right,
+            (LightColor::Red, true) => self.env_left_boost,
+            (LightColor::Blue, true) => self.env_right_boost,
+            (LightColor::White, false) => self.env_white.unwrap_or([1.0; 4]),
+            (LightColor::White, true) => self.env_white_boost.or(self.env_white).unwrap_or([1.0; 4]),
+        }
+    }
 }
 
 /// Holds info about a particular beatmap (one difficulty of a map)
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Beatmap {
     pub version: String,
     pub events: Vec<Event>,
@@ -49,22 +155,25 @@ pub struct Beatmap {
     pub basic_event_types_with_keywords: HashMap<String, serde_json::Value>,
     pub use_normal_events_as_compatible_events: bool,
     pub custom_data: HashMap<String, serde_json::Value>,
+    pub extras: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BPMEvent {
     pub beat: f64,
     pub value: f64,
+    pub extras: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Rotation {
     pub beat: f64,
     pub is_late: bool,
     pub value: f64,
+    pub extras: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Note {
     pub beat: f64,
     pub x: i32,
@@ -72,16 +181,20 @@ pub struct Note {
     pub color: NoteColor,
     pub direction: Direction,
     pub angle_offset: f64,
+    pub custom_data: HashMap<String, serde_json::Value>,
+    pub extras: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Bomb {
     pub beat: f64,
     pub x: i32,
     pub y: i32,
+    pub custom_data: HashMap<String, serde_json::Value>,
+    pub extras: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Obstacle {
     pub beat: f64,
     pub x: i32,
@@ -89,9 +202,11 @@ pub struct Obstacle {
     pub duration: f64,
     pub width: f64,
     pub height: f64,
+    pub custom_data: HashMap<String, serde_json::Value>,
+    pub extras: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Slider {
     pub head_beat: f64,
     pub color: NoteColor,
@@ -105,9 +220,11 @@ pub struct Slider {
     pub tail_direction: Direction,
     pub tail_bulge: f64,
     pub special_curving: SliderMidAnchorMode,
+    pub custom_data: HashMap<String, serde_json::Value>,
+    pub extras: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BurstSlider {
     pub head_beat: f64,
     pub color: NoteColor,
@@ -119,24 +236,40 @@ pub struct BurstSlider {
     pub tail_y: i32,
     pub segment_count: i32,
     pub squish: f64,
+    pub custom_data: HashMap<String, serde_json::Value>,
+    pub extras: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BasicEvent {
     pub beat: f64,
     pub kind: i32,
     pub value: i32,
     pub float_value: Option<f64>,
     pub custom_data: HashMap<String, serde_json::Value>,
+    pub extras: HashMap<String, serde_json::Value>,
+}
+
+impl BasicEvent {
+    /// The typed `kind`, or the raw number if it isn't a known type
+    pub fn event_type(&self) -> std::result::Result<BasicEventType, i32> {
+        BasicEventType::try_from(self.kind)
+    }
+
+    /// The typed `value` of a light event. Only meaningful when [BasicEventType::is_light] holds for the type
+    pub fn light_value(&self) -> std::result::Result<LightValue, i32> {
+        LightValue::try_from(self.value)
+    }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ColorBoost {
     pub beat: f64,
     pub enable: bool,
+    pub extras: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Event {
     BPM(Box<BPMEvent>),
     Rotation(Box<Rotation>),
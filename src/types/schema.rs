@@ -4,7 +4,7 @@ use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 use serde_repr::{Serialize_repr, Deserialize_repr};
 use crate::error::{Error, Result};
-use crate::types::common::{Axis, BoxFilterOrdering, Direction, DistributionKind, Easing, LightColor, RotationBehaviour, RotationDirection, LimitKind, NoteColor, SliderMidAnchorMode, TransitionKind, Difficulty, read_string_from_file, BoxFilterKind};
+use crate::types::common::{Axis, BoxFilterOrdering, Direction, DistributionKind, Easing, LightColor, RotationBehaviour, RotationDirection, LimitKind, NoteColor, SliderMidAnchorMode, TransitionKind, Difficulty, read_string_from_file, write_string_to_file, BoxFilterKind};
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct Info {
@@ -26,7 +26,9 @@ pub struct Info {
     pub _songTimeOffset: f64,
     #[serde(skip_serializing_if = "HashMap::is_empty", default)]
     pub _customData: HashMap<String, serde_json::Value>,
-    pub _difficultyBeatmapSets: Vec<DifficultyBeatmapSet>
+    pub _difficultyBeatmapSets: Vec<DifficultyBeatmapSet>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 impl Info {
@@ -37,12 +39,60 @@ impl Info {
     pub fn read_from_file(path: &str) -> Result<Self> {
         Self::read_from_str(&read_string_from_file(path)?)
     }
+
+    pub fn write_to_string(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(Error::SerializationFailed)
+    }
+
+    pub fn write_to_file(&self, path: &str) -> Result<()> {
+        write_string_to_file(path, &self.write_to_string()?)
+    }
+}
+
+/// Either version of `Info.dat`, told apart by the version key
+#[derive(Debug, PartialEq)]
+pub enum InfoFile {
+    Old(Box<Info>),
+    V4(Box<V4Info>)
+}
+
+impl InfoFile {
+    pub fn read_from_str(data: &str) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Version {
+            version: Option<String>,
+        }
+
+        let version: Version = serde_json::from_str(data)?;
+        if version.version.map(|x| x.starts_with('4')).unwrap_or(false) {
+            Ok(Self::V4(Box::new(serde_json::from_str(data)?)))
+        } else {
+            Ok(Self::Old(Box::new(serde_json::from_str(data)?)))
+        }
+    }
+
+    pub fn read_from_file(path: &str) -> Result<Self> {
+        Self::read_from_str(&read_string_from_file(path)?)
+    }
+
+    pub fn write_to_string(&self) -> Result<String> {
+        match self {
+            Self::Old(info) => serde_json::to_string(info),
+            Self::V4(info) => serde_json::to_string(info),
+        }.map_err(Error::SerializationFailed)
+    }
+
+    pub fn write_to_file(&self, path: &str) -> Result<()> {
+        write_string_to_file(path, &self.write_to_string()?)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct DifficultyBeatmapSet {
     pub _beatmapCharacteristicName: String,
-    pub _difficultyBeatmaps: Vec<DifficultyBeatmap>
+    pub _difficultyBeatmaps: Vec<DifficultyBeatmap>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -54,30 +104,157 @@ pub struct DifficultyBeatmap {
     pub _noteJumpStartBeatOffset: f64,
     #[serde(skip_serializing_if = "HashMap::is_empty", default)]
     pub _customData: HashMap<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4Info {
+    pub version: String,
+    pub song: V4InfoSong,
+    pub audio: V4InfoAudio,
+    pub songPreviewFilename: String,
+    pub coverImageFilename: String,
+    pub environmentNames: Vec<String>,
+    #[serde(default)]
+    pub colorSchemes: Vec<V4ColorScheme>,
+    pub difficultyBeatmaps: Vec<V4InfoDifficultyBeatmap>,
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    pub customData: HashMap<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+impl V4Info {
+    pub fn read_from_str(data: &str) -> Result<Self> {
+        Ok(serde_json::from_str(data)?)
+    }
+
+    pub fn read_from_file(path: &str) -> Result<Self> {
+        Self::read_from_str(&read_string_from_file(path)?)
+    }
+
+    pub fn write_to_string(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(Error::SerializationFailed)
+    }
+
+    pub fn write_to_file(&self, path: &str) -> Result<()> {
+        write_string_to_file(path, &self.write_to_string()?)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4InfoSong {
+    pub title: String,
+    pub subTitle: String,
+    pub author: String,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4InfoAudio {
+    pub songFilename: String,
+    pub songDuration: f64,
+    pub audioDataFilename: String,
+    pub bpm: f64,
+    #[serde(default)]
+    pub lufs: f64,
+    pub previewStartTime: f64,
+    pub previewDuration: f64,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+/// Colors are hex strings, `RRGGBBAA`
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4ColorScheme {
+    pub colorSchemeName: String,
+    pub overrideNotes: bool,
+    pub saberAColor: String,
+    pub saberBColor: String,
+    pub obstaclesColor: String,
+    pub overrideLights: bool,
+    pub environmentColor0: String,
+    pub environmentColor1: String,
+    #[serde(skip_serializing_if = "String::is_empty", default)]
+    pub environmentColorW: String,
+    pub environmentColor0Boost: String,
+    pub environmentColor1Boost: String,
+    #[serde(skip_serializing_if = "String::is_empty", default)]
+    pub environmentColorWBoost: String,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4InfoDifficultyBeatmap {
+    pub characteristic: String,
+    pub difficulty: Difficulty,
+    pub beatmapAuthors: V4BeatmapAuthors,
+    pub environmentNameIdx: usize,
+    #[serde(default)]
+    pub beatmapColorSchemeIdx: usize,
+    pub noteJumpMovementSpeed: f64,
+    pub noteJumpStartBeatOffset: f64,
+    pub beatmapDataFilename: String,
+    #[serde(default)]
+    pub lightshowDataFilename: String,
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    pub customData: HashMap<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4BeatmapAuthors {
+    #[serde(default)]
+    pub mappers: Vec<String>,
+    #[serde(default)]
+    pub lighters: Vec<String>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 #[derive(Debug, PartialEq)]
 pub enum BeatmapFile {
-    Old(OldBeatmapFile),
-    New(NewBeatmapFile)
+    Old(Box<OldBeatmapFile>),
+    New(Box<NewBeatmapFile>),
+    /// Only the difficulty file, lights are kept in a separate [V4LightshowFile]
+    V4(Box<V4BeatmapFile>)
 }
 
 impl BeatmapFile {
     pub fn read_from_str(data: &str) -> Result<Self> {
         let new = serde_json::from_str(data);
         let old = serde_json::from_str(data);
+        let v4 = serde_json::from_str(data);
         if let Ok(beatmap) = new {
-            Ok(Self::New(beatmap))
+            Ok(Self::New(Box::new(beatmap)))
         } else if let Ok(beatmap) = old {
-            Ok(Self::Old(beatmap))
+            Ok(Self::Old(Box::new(beatmap)))
+        } else if let Ok(beatmap) = v4 {
+            Ok(Self::V4(Box::new(beatmap)))
         } else {
-            Err(Error::BeatmapParsingFailed { err_as_new: new.unwrap_err(), err_as_old: old.unwrap_err() })
+            Err(Error::BeatmapParsingFailed { err_as_new: new.unwrap_err(), err_as_old: old.unwrap_err(), err_as_v4: v4.unwrap_err() })
         }
     }
 
     pub fn read_from_file(path: &str) -> Result<Self> {
         Self::read_from_str(&read_string_from_file(path)?)
     }
+
+    pub fn write_to_string(&self) -> Result<String> {
+        match self {
+            Self::Old(beatmap) => serde_json::to_string(beatmap),
+            Self::New(beatmap) => serde_json::to_string(beatmap),
+            Self::V4(beatmap) => serde_json::to_string(beatmap),
+        }.map_err(Error::SerializationFailed)
+    }
+
+    pub fn write_to_file(&self, path: &str) -> Result<()> {
+        write_string_to_file(path, &self.write_to_string()?)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -101,25 +278,45 @@ pub struct NewBeatmapFile {
     pub basicEventTypesWithKeywords: HashMap<String, serde_json::Value>,
     pub useNormalEventsAsCompatibleEvents: bool,
     #[serde(skip_serializing_if = "HashMap::is_empty", default)]
-    pub customData: HashMap<String, serde_json::Value>
+    pub customData: HashMap<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct BpmEvent {
     pub b: f64,
-    pub m: f64
+    pub m: f64,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 fn bool_to_int<S>(value: &bool, serialized: S) -> core::result::Result<S::Ok, S::Error> where S: serde::Serializer {
     serialized.serialize_i32(*value as i32)
 }
 
+fn bool_from_int<'de, D>(deserializer: D) -> core::result::Result<bool, D::Error> where D: serde::Deserializer<'de> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BoolOrInt {
+        Bool(bool),
+        Int(i64)
+    }
+
+    Ok(match BoolOrInt::deserialize(deserializer)? {
+        BoolOrInt::Bool(value) => value,
+        BoolOrInt::Int(value) => value != 0
+    })
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct RotationEvent {
     pub b: f64,
-    #[serde(serialize_with = "bool_to_int")]
+    #[serde(serialize_with = "bool_to_int", deserialize_with = "bool_from_int")]
     pub e: bool,
-    pub r: f64
+    pub r: f64,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -129,14 +326,22 @@ pub struct ColorNote {
     pub y: i32,
     pub c: NoteColor,
     pub d: Direction,
-    pub a: i32
+    pub a: i32,
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    pub customData: HashMap<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct BombNote {
     pub b: f64,
     pub x: i32,
-    pub y: i32
+    pub y: i32,
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    pub customData: HashMap<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -146,7 +351,11 @@ pub struct Obstacle {
     pub y: i32,
     pub d: f64,
     pub w: f64,
-    pub h: f64
+    pub h: f64,
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    pub customData: HashMap<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -162,7 +371,11 @@ pub struct Slider {
     pub ty: i32,
     pub tc: Direction,
     pub tmu: f64,
-    pub m: SliderMidAnchorMode
+    pub m: SliderMidAnchorMode,
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    pub customData: HashMap<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -176,7 +389,11 @@ pub struct BurstSlider {
     pub tx: i32,
     pub ty: i32,
     pub sc: i32,
-    pub s: f64
+    pub s: f64,
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    pub customData: HashMap<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -185,20 +402,28 @@ pub struct BasicBeatmapEvent {
     pub et: i32,
     pub i: i32,
     #[serde(skip_serializing_if = "Option::is_none", default)]
-    pub f: Option<f64>
+    pub f: Option<f64>,
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    pub customData: HashMap<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct ColorBoostBeatmapEvent {
     pub b: f64,
-    pub o: bool
+    pub o: bool,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct LightColorEventBoxGroup {
     pub b: f64,
     pub g: i32,
-    pub e: Vec<LightColorEventBoxGroupLane>
+    pub e: Vec<LightColorEventBoxGroupLane>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -208,11 +433,13 @@ pub struct LightColorEventBoxGroupLane {
     pub d: DistributionKind,
     pub r: f64,
     pub t: DistributionKind,
-    #[serde(serialize_with = "bool_to_int")]
+    #[serde(serialize_with = "bool_to_int", deserialize_with = "bool_from_int")]
     pub b: bool,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub i: Option<Easing>,
-    pub e: Vec<LightColorEventData>
+    pub e: Vec<LightColorEventData>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -221,14 +448,18 @@ pub struct LightColorEventData {
     pub i: TransitionKind,
     pub c: LightColor,
     pub s: f64,
-    pub f: i32
+    pub f: i32,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct LightRotationEventBoxGroup {
     pub b: f64,
     pub g: i32,
-    pub e: Vec<LightRotationEventBoxGroupLane>
+    pub e: Vec<LightRotationEventBoxGroupLane>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -238,14 +469,16 @@ pub struct LightRotationEventBoxGroupLane {
     pub d: DistributionKind,
     pub s: f64,
     pub t: DistributionKind,
-    #[serde(serialize_with = "bool_to_int")]
+    #[serde(serialize_with = "bool_to_int", deserialize_with = "bool_from_int")]
     pub b: bool,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub i: Option<Easing>,
     pub a: Axis,
-    #[serde(serialize_with = "bool_to_int")]
+    #[serde(serialize_with = "bool_to_int", deserialize_with = "bool_from_int")]
     pub r: bool,
-    pub e: Vec<LightRotationEventData>
+    pub e: Vec<LightRotationEventData>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -255,14 +488,18 @@ pub struct LightRotationEventData {
     pub l: i32,
     pub e: Easing,
     pub r: f64,
-    pub o: RotationDirection
+    pub o: RotationDirection,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct LightTranslationEventBoxGroup {
     pub b: f64,
     pub g: i32,
-    pub e: Vec<LightTranslationEventBoxGroupLane>
+    pub e: Vec<LightTranslationEventBoxGroupLane>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -272,14 +509,16 @@ pub struct LightTranslationEventBoxGroupLane {
     pub d: DistributionKind,
     pub s: f64,
     pub t: DistributionKind,
-    #[serde(serialize_with = "bool_to_int")]
+    #[serde(serialize_with = "bool_to_int", deserialize_with = "bool_from_int")]
     pub b: bool,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub i: Option<Easing>,
     pub a: Axis,
-    #[serde(serialize_with = "bool_to_int")]
+    #[serde(serialize_with = "bool_to_int", deserialize_with = "bool_from_int")]
     pub r: bool,
-    pub l: Vec<LightTranslationEventData>
+    pub l: Vec<LightTranslationEventData>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -287,21 +526,25 @@ pub struct LightTranslationEventData {
     pub b: f64,
     pub p: RotationBehaviour,
     pub e: Easing,
-    pub t: f64
+    pub t: f64,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct FilterObject {
     pub c: i32,
     pub f: BoxFilterKind,
     pub p: i32,
     pub t: i32,
-    #[serde(serialize_with = "bool_to_int")]
+    #[serde(serialize_with = "bool_to_int", deserialize_with = "bool_from_int")]
     pub r: bool,
     pub n: BoxFilterOrdering,
     pub s: i32,
     pub l: f64,
-    pub d: LimitKind
+    pub d: LimitKind,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -314,8 +557,10 @@ pub struct OldBeatmapFile {
     pub _events: Vec<OldEvent>,
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub _waypoints: Vec<serde_json::Value>,
-    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
-    pub customData: HashMap<String, serde_json::Value>
+    #[serde(rename = "_customData", alias = "customData", skip_serializing_if = "HashMap::is_empty", default)]
+    pub customData: HashMap<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 #[derive(Serialize_repr, Deserialize_repr, Debug, PartialEq)]
@@ -335,7 +580,9 @@ pub struct OldNote {
     pub _type: OldNoteKind,
     pub _cutDirection: Direction,
     #[serde(skip_serializing_if = "HashMap::is_empty", default)]
-    pub _customData: HashMap<String, serde_json::Value>
+    pub _customData: HashMap<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -353,7 +600,9 @@ pub struct OldSlider {
     pub _tailCutDirection: Direction,
     pub _sliderMidAnchorMode: SliderMidAnchorMode,
     #[serde(skip_serializing_if = "HashMap::is_empty", default)]
-    pub _customData: HashMap<String, serde_json::Value>
+    pub _customData: HashMap<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 #[derive(Serialize_repr, Deserialize_repr, Debug, PartialEq)]
@@ -371,7 +620,9 @@ pub struct OldObstacle {
     pub _duration: f64,
     pub _width: f64,
     #[serde(skip_serializing_if = "HashMap::is_empty", default)]
-    pub _customData: HashMap<String, serde_json::Value>
+    pub _customData: HashMap<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -382,6 +633,432 @@ pub struct OldEvent {
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub _floatValue: Option<f64>,
     #[serde(skip_serializing_if = "HashMap::is_empty", default)]
-    pub _customData: HashMap<String, serde_json::Value>
+    pub _customData: HashMap<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4BeatmapFile {
+    pub version: String,
+    pub colorNotes: Vec<V4Object>,
+    pub colorNotesData: Vec<V4ColorNoteData>,
+    #[serde(default)]
+    pub bombNotes: Vec<V4Object>,
+    #[serde(default)]
+    pub bombNotesData: Vec<V4BombNoteData>,
+    #[serde(default)]
+    pub obstacles: Vec<V4Object>,
+    #[serde(default)]
+    pub obstaclesData: Vec<V4ObstacleData>,
+    #[serde(default)]
+    pub arcs: Vec<V4Arc>,
+    #[serde(default)]
+    pub arcsData: Vec<V4ArcData>,
+    #[serde(default)]
+    pub chains: Vec<V4Chain>,
+    #[serde(default)]
+    pub chainsData: Vec<V4ChainData>,
+    #[serde(default)]
+    pub spawnRotations: Vec<V4Event>,
+    #[serde(default)]
+    pub spawnRotationsData: Vec<V4SpawnRotationData>,
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    pub customData: HashMap<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+/// A note, bomb or obstacle. `i` indexes the matching data array, `r` is the rotation lane (for 90/360 degree maps)
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4Object {
+    pub b: f64,
+    #[serde(default)]
+    pub r: i32,
+    pub i: usize,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+/// An event or waypoint, `i` indexes the matching data array
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4Event {
+    pub b: f64,
+    pub i: usize,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4ColorNoteData {
+    pub x: i32,
+    pub y: i32,
+    pub c: NoteColor,
+    pub d: Direction,
+    #[serde(default)]
+    pub a: i32,
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    pub customData: HashMap<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4BombNoteData {
+    pub x: i32,
+    pub y: i32,
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    pub customData: HashMap<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4ObstacleData {
+    pub d: f64,
+    pub x: i32,
+    pub y: i32,
+    pub w: f64,
+    pub h: f64,
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    pub customData: HashMap<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+/// Head and tail are indices into `colorNotesData`, `ai` into `arcsData`
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4Arc {
+    pub hb: f64,
+    pub tb: f64,
+    #[serde(default)]
+    pub hr: i32,
+    #[serde(default)]
+    pub tr: i32,
+    pub hi: usize,
+    pub ti: usize,
+    pub ai: usize,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4ArcData {
+    pub m: f64,
+    pub tm: f64,
+    pub a: SliderMidAnchorMode,
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    pub customData: HashMap<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+/// The head is an index into `colorNotesData`, `ci` into `chainsData`
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4Chain {
+    pub hb: f64,
+    pub tb: f64,
+    #[serde(default)]
+    pub hr: i32,
+    #[serde(default)]
+    pub tr: i32,
+    pub i: usize,
+    pub ci: usize,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4ChainData {
+    pub tx: i32,
+    pub ty: i32,
+    pub c: i32,
+    pub s: f64,
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    pub customData: HashMap<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4SpawnRotationData {
+    /// 0 for early, 1 for late
+    pub t: i32,
+    pub r: f64,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4LightshowFile {
+    pub version: String,
+    #[serde(default)]
+    pub waypoints: Vec<V4Event>,
+    #[serde(default)]
+    pub waypointsData: Vec<V4WaypointData>,
+    #[serde(default)]
+    pub basicEvents: Vec<V4Event>,
+    #[serde(default)]
+    pub basicEventsData: Vec<V4BasicEventData>,
+    #[serde(default)]
+    pub colorBoostEvents: Vec<V4Event>,
+    #[serde(default)]
+    pub colorBoostEventsData: Vec<V4ColorBoostEventData>,
+    #[serde(default)]
+    pub eventBoxGroups: Vec<V4EventBoxGroup>,
+    #[serde(default)]
+    pub indexFilters: Vec<FilterObject>,
+    #[serde(default)]
+    pub lightColorEventBoxes: Vec<V4EventBoxData>,
+    #[serde(default)]
+    pub lightColorEvents: Vec<V4LightColorEventData>,
+    #[serde(default)]
+    pub lightRotationEventBoxes: Vec<V4AxisEventBoxData>,
+    #[serde(default)]
+    pub lightRotationEvents: Vec<V4LightRotationEventData>,
+    #[serde(default)]
+    pub lightTranslationEventBoxes: Vec<V4AxisEventBoxData>,
+    #[serde(default)]
+    pub lightTranslationEvents: Vec<V4LightTranslationEventData>,
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    pub basicEventTypesWithKeywords: HashMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub useNormalEventsAsCompatibleEvents: bool,
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    pub customData: HashMap<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+impl V4LightshowFile {
+    pub fn read_from_str(data: &str) -> Result<Self> {
+        serde_json::from_str(data).map_err(Error::LightshowParsingFailed)
+    }
+
+    pub fn read_from_file(path: &str) -> Result<Self> {
+        Self::read_from_str(&read_string_from_file(path)?)
+    }
+
+    pub fn write_to_string(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(Error::SerializationFailed)
+    }
+
+    pub fn write_to_file(&self, path: &str) -> Result<()> {
+        write_string_to_file(path, &self.write_to_string()?)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4WaypointData {
+    pub x: i32,
+    pub y: i32,
+    pub d: i32,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4BasicEventData {
+    pub t: i32,
+    pub i: i32,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub f: Option<f64>,
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    pub customData: HashMap<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4ColorBoostEventData {
+    #[serde(serialize_with = "bool_to_int", deserialize_with = "bool_from_int")]
+    pub b: bool,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+#[derive(Serialize_repr, Deserialize_repr, Debug, Clone, Copy, PartialEq)]
+#[repr(i8)]
+pub enum V4EventBoxGroupKind {
+    Color = 1,
+    Rotation = 2,
+    Translation = 3,
+    FloatFx = 4,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4EventBoxGroup {
+    pub t: V4EventBoxGroupKind,
+    pub b: f64,
+    pub g: i32,
+    pub e: Vec<V4EventBox>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+/// `f` indexes `indexFilters`, `e` the event box array of the group's kind, and each of `l` the matching event array
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4EventBox {
+    pub f: usize,
+    pub e: usize,
+    pub l: Vec<V4Event>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4EventBoxData {
+    pub w: f64,
+    pub d: DistributionKind,
+    pub s: f64,
+    pub t: DistributionKind,
+    #[serde(serialize_with = "bool_to_int", deserialize_with = "bool_from_int")]
+    pub b: bool,
+    pub e: Easing,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
 }
 
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4AxisEventBoxData {
+    pub w: f64,
+    pub d: DistributionKind,
+    pub s: f64,
+    pub t: DistributionKind,
+    #[serde(serialize_with = "bool_to_int", deserialize_with = "bool_from_int")]
+    pub b: bool,
+    pub e: Easing,
+    pub a: Axis,
+    #[serde(serialize_with = "bool_to_int", deserialize_with = "bool_from_int")]
+    pub f: bool,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+/// `p` is 1 when the event extends the previous one, `e` is -1 for an instant change
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4LightColorEventData {
+    pub p: i32,
+    pub c: LightColor,
+    pub e: Easing,
+    pub b: f64,
+    pub f: i32,
+    #[serde(default)]
+    pub sb: f64,
+    #[serde(default)]
+    pub sf: i32,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4LightRotationEventData {
+    pub p: RotationBehaviour,
+    pub e: Easing,
+    pub r: f64,
+    pub d: RotationDirection,
+    pub l: i32,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4LightTranslationEventData {
+    pub p: RotationBehaviour,
+    pub e: Easing,
+    pub t: f64,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+/// Either version of the audio data file (`BPMInfo.dat` or `AudioData.dat`), told apart by the version key
+#[derive(Debug, PartialEq)]
+pub enum AudioDataFile {
+    Old(Box<OldAudioData>),
+    V4(Box<V4AudioData>)
+}
+
+impl AudioDataFile {
+    pub fn read_from_str(data: &str) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Version {
+            version: Option<String>,
+        }
+
+        let version: Version = serde_json::from_str(data).map_err(Error::AudioDataParsingFailed)?;
+        if version.version.map(|x| x.starts_with('4')).unwrap_or(false) {
+            Ok(Self::V4(Box::new(serde_json::from_str(data).map_err(Error::AudioDataParsingFailed)?)))
+        } else {
+            Ok(Self::Old(Box::new(serde_json::from_str(data).map_err(Error::AudioDataParsingFailed)?)))
+        }
+    }
+
+    pub fn read_from_file(path: &str) -> Result<Self> {
+        Self::read_from_str(&read_string_from_file(path)?)
+    }
+
+    pub fn write_to_string(&self) -> Result<String> {
+        match self {
+            Self::Old(data) => serde_json::to_string(data),
+            Self::V4(data) => serde_json::to_string(data),
+        }.map_err(Error::SerializationFailed)
+    }
+
+    pub fn write_to_file(&self, path: &str) -> Result<()> {
+        write_string_to_file(path, &self.write_to_string()?)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct OldAudioData {
+    pub _version: String,
+    pub _songSampleCount: u64,
+    pub _songFrequency: u32,
+    pub _regions: Vec<OldBpmRegion>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct OldBpmRegion {
+    pub _startSampleIndex: u64,
+    pub _endSampleIndex: u64,
+    pub _startBeat: f64,
+    pub _endBeat: f64,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4AudioData {
+    pub version: String,
+    #[serde(default)]
+    pub songChecksum: String,
+    pub songSampleCount: u64,
+    pub songFrequency: u32,
+    pub bpmData: Vec<V4BpmData>,
+    #[serde(default)]
+    pub lufsData: Vec<V4LufsData>,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4BpmData {
+    pub si: u64,
+    pub ei: u64,
+    pub sb: f64,
+    pub eb: f64,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct V4LufsData {
+    pub si: u64,
+    pub ei: u64,
+    pub l: f64,
+    #[serde(flatten)]
+    pub extras: HashMap<String, serde_json::Value>
+}
@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+use serde_json::{json, Value};
+use crate::types::custom_data::{as_strings, HasCustomData};
+use crate::types::primary::{BeatmapMeta, BeatmapSetMeta, ColorScheme};
+
+/// SongCore keys are `_`-prefixed in `Info.dat` 2.x and bare in 4.x. Reads either
+fn songcore_value<'a>(data: &'a (impl HasCustomData + ?Sized), key: &str) -> Option<&'a Value> {
+    data.custom_value(&format!("_{key}"), key)
+}
+
+/// SongCore keys of a difficulty and of the level, without the prefix
+const SONGCORE_KEYS: [&str; 18] = [
+    "difficultyLabel", "requirements", "suggestions", "warnings", "information",
+    "colorLeft", "colorRight", "envColorLeft", "envColorRight", "envColorWhite",
+    "envColorLeftBoost", "envColorRightBoost", "envColorWhiteBoost", "obstacleColor",
+    "contributors", "customEnvironment", "customEnvironmentHash", "editors",
+];
+
+fn key_name(key: &str, bare: bool) -> String {
+    if bare { key.to_string() } else { format!("_{key}") }
+}
+
+/// Writes the bare key if `bare`, the `_`-prefixed one otherwise, and removes the other. `None` removes both
+fn set_songcore_value(data: &mut (impl HasCustomData + ?Sized), key: &str, value: Option<Value>, bare: bool) {
+    let custom_data = data.custom_data_mut();
+    custom_data.remove(&key_name(key, !bare));
+    match value {
+        Some(value) => custom_data.insert(key_name(key, bare), value),
+        None => custom_data.remove(&key_name(key, bare)),
+    };
+}
+
+/// Renames the SongCore keys of the level or of a difficulty (contributors included) to the style of `Info.dat` 4.x if `bare`, 2.x otherwise
+pub(crate) fn songcore_keys_to_version(data: &HashMap<String, Value>, bare: bool) -> HashMap<String, Value> {
+    let mut result = data.clone();
+    for key in SONGCORE_KEYS {
+        if let Some(value) = result.remove(&key_name(key, !bare)) {
+            result.entry(key_name(key, bare)).or_insert(value);
+        }
+    }
+    if let Some(Value::Array(contributors)) = result.get_mut(&key_name("contributors", bare)) {
+        for contributor in contributors.iter_mut().filter_map(|x| x.as_object_mut()) {
+            for key in ["role", "name", "iconPath"] {
+                if let Some(value) = contributor.remove(&key_name(key, !bare)) {
+                    contributor.entry(key_name(key, bare)).or_insert(value);
+                }
+            }
+        }
+    }
+    result
+}
+
+fn color_from_value(value: &Value) -> Option<[f64; 4]> {
+    let channel = |key: &str| value.get(key)?.as_f64();
+    Some([channel("r")?, channel("g")?, channel("b")?, channel("a").unwrap_or(1.0)])
+}
+
+fn color_to_value([r, g, b, a]: [f64; 4]) -> Value {
+    if a == 1.0 { json!({ "r": r, "g": g, "b": b }) } else { json!({ "r": r, "g": g, "b": b, "a": a }) }
+}
+
+/// Color overrides of a difficulty, RGBA. Missing ones use the environment's colors
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SongCoreColors {
+    pub left: Option<[f64; 4]>,
+    pub right: Option<[f64; 4]>,
+    pub env_left: Option<[f64; 4]>,
+    pub env_right: Option<[f64; 4]>,
+    pub env_white: Option<[f64; 4]>,
+    pub env_left_boost: Option<[f64; 4]>,
+    pub env_right_boost: Option<[f64; 4]>,
+    pub env_white_boost: Option<[f64; 4]>,
+    pub obstacle: Option<[f64; 4]>,
+}
+
+impl SongCoreColors {
+    const KEYS: [&'static str; 9] = [
+        "colorLeft", "colorRight", "envColorLeft", "envColorRight", "envColorWhite",
+        "envColorLeftBoost", "envColorRightBoost", "envColorWhiteBoost", "obstacleColor",
+    ];
+
+    fn fields(&mut self) -> [&mut Option<[f64; 4]>; 9] {
+        [
+            &mut self.left, &mut self.right, &mut self.env_left, &mut self.env_right, &mut self.env_white,
+            &mut self.env_left_boost, &mut self.env_right_boost, &mut self.env_white_boost, &mut self.obstacle,
+        ]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contributor {
+    pub role: String,
+    pub name: String,
+    /// Relative to the level folder
+    pub icon_path: String,
+}
+
+/// Which editors touched the level
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Editors {
+    pub last_edited_by: Option<String>,
+    /// Per-editor data (usually a version), keyed by editor name
+    pub editors: HashMap<String, Value>,
+}
+
+/// Typed access to the [SongCore](https://github.com/Kylemc1413/SongCore) keys of a difficulty in `Info.dat`
+pub trait SongCoreDifficultyData: HasCustomData {
+    /// Whether SongCore keys are written bare, as in `Info.dat` 4.x. A difficulty doesn't know the version of its info, so this goes by the keys it already has.
+    /// [BeatmapSetMeta] renames them to match its version when it's written
+    fn uses_bare_keys(&self) -> bool {
+        self.custom_data().keys().any(|x| SONGCORE_KEYS.contains(&x.as_str()))
+    }
+
+    fn difficulty_label(&self) -> Option<&str> {
+        songcore_value(self, "difficultyLabel")?.as_str()
+    }
+
+    fn set_difficulty_label(&mut self, value: Option<&str>) {
+        set_songcore_value(self, "difficultyLabel", value.map(|x| json!(x)), self.uses_bare_keys());
+    }
+
+    /// Mods the difficulty can't be played without, e.g. `Noodle Extensions`
+    fn requirements(&self) -> Vec<String> {
+        songcore_value(self, "requirements").and_then(as_strings).unwrap_or_default()
+    }
+
+    fn set_requirements(&mut self, value: Vec<String>) {
+        set_songcore_value(self, "requirements", Some(json!(value)).filter(|_| !value.is_empty()), self.uses_bare_keys());
+    }
+
+    /// Whether `requirement` (e.g. `Chroma`) is listed as a requirement
+    fn requires(&self, requirement: &str) -> bool {
+        self.requirements().iter().any(|x| x == requirement)
+    }
+
+    /// Mods the difficulty is meant to be played with, e.g. `Chroma`
+    fn suggestions(&self) -> Vec<String> {
+        songcore_value(self, "suggestions").and_then(as_strings).unwrap_or_default()
+    }
+
+    fn set_suggestions(&mut self, value: Vec<String>) {
+        set_songcore_value(self, "suggestions", Some(json!(value)).filter(|_| !value.is_empty()), self.uses_bare_keys());
+    }
+
+    fn warnings(&self) -> Vec<String> {
+        songcore_value(self, "warnings").and_then(as_strings).unwrap_or_default()
+    }
+
+    fn set_warnings(&mut self, value: Vec<String>) {
+        set_songcore_value(self, "warnings", Some(json!(value)).filter(|_| !value.is_empty()), self.uses_bare_keys());
+    }
+
+    fn information(&self) -> Vec<String> {
+        songcore_value(self, "information").and_then(as_strings).unwrap_or_default()
+    }
+
+    fn set_information(&mut self, value: Vec<String>) {
+        set_songcore_value(self, "information", Some(json!(value)).filter(|_| !value.is_empty()), self.uses_bare_keys());
+    }
+
+    fn colors(&self) -> SongCoreColors {
+        let mut colors = SongCoreColors::default();
+        for (key, field) in SongCoreColors::KEYS.iter().zip(colors.fields()) {
+            *field = songcore_value(self, key).and_then(color_from_value);
+        }
+        colors
+    }
+
+    /// Missing colors are removed from the map
+    fn set_colors(&mut self, mut colors: SongCoreColors) {
+        let bare = self.uses_bare_keys();
+        for (key, field) in SongCoreColors::KEYS.iter().zip(colors.fields()) {
+            set_songcore_value(self, key, field.map(color_to_value), bare);
+        }
+    }
+}
+
+/// Typed access to the [SongCore](https://github.com/Kylemc1413/SongCore) keys of the level in `Info.dat`
+pub trait SongCoreLevelData: HasCustomData {
+    /// Whether SongCore keys are written bare, as in `Info.dat` 4.x
+    fn uses_bare_keys(&self) -> bool;
+
+    fn contributors(&self) -> Vec<Contributor> {
+        let Some(Value::Array(contributors)) = songcore_value(self, "contributors") else { return vec![] };
+        contributors.iter().map(|x| {
+            let field = |key: &str| x.get(format!("_{key}")).or_else(|| x.get(key)).and_then(|x| x.as_str()).unwrap_or_default().to_string();
+            Contributor { role: field("role"), name: field("name"), icon_path: field("iconPath") }
+        }).collect()
+    }
+
+    fn set_contributors(&mut self, value: Vec<Contributor>) {
+        let bare = self.uses_bare_keys();
+        let value: Vec<Value> = value.into_iter()
+            .map(|x| json!({ key_name("role", bare): x.role, key_name("name", bare): x.name, key_name("iconPath", bare): x.icon_path }))
+            .collect();
+        set_songcore_value(self, "contributors", Some(json!(value)).filter(|_| !value.is_empty()), bare);
+    }
+
+    /// Name of the custom platform the level is meant to be played on
+    fn custom_environment(&self) -> Option<&str> {
+        songcore_value(self, "customEnvironment")?.as_str()
+    }
+
+    fn set_custom_environment(&mut self, value: Option<&str>) {
+        set_songcore_value(self, "customEnvironment", value.map(|x| json!(x)), self.uses_bare_keys());
+    }
+
+    fn custom_environment_hash(&self) -> Option<&str> {
+        songcore_value(self, "customEnvironmentHash")?.as_str()
+    }
+
+    fn set_custom_environment_hash(&mut self, value: Option<&str>) {
+        set_songcore_value(self, "customEnvironmentHash", value.map(|x| json!(x)), self.uses_bare_keys());
+    }
+
+    fn editors(&self) -> Option<Editors> {
+        let mut editors = songcore_value(self, "editors")?.as_object()?.clone();
+        let last_edited_by = editors.remove("_lastEditedBy").and_then(|x| x.as_str().map(String::from));
+        Some(Editors { last_edited_by, editors: editors.into_iter().collect() })
+    }
+
+    fn set_editors(&mut self, value: Option<Editors>) {
+        let value = value.map(|x| {
+            let mut editors: serde_json::Map<String, Value> = x.editors.into_iter().collect();
+            if let Some(last_edited_by) = x.last_edited_by {
+                editors.insert("_lastEditedBy".to_string(), json!(last_edited_by));
+            }
+            Value::Object(editors)
+        });
+        set_songcore_value(self, "editors", value, self.uses_bare_keys());
+    }
+}
+
+impl SongCoreDifficultyData for BeatmapMeta {}
+impl SongCoreLevelData for BeatmapSetMeta {
+    fn uses_bare_keys(&self) -> bool {
+        self.version.starts_with('4')
+    }
+}
+
+impl ColorScheme {
+    /// This scheme with the SongCore overrides on top. Boost colors default to the overridden normal ones
+    pub fn with_overrides(&self, colors: &SongCoreColors) -> ColorScheme {
+        let mut scheme = self.clone();
+        if colors.left.is_some() || colors.right.is_some() || colors.obstacle.is_some() {
+            scheme.override_notes = true;
+        }
+        if colors.env_left.is_some() || colors.env_right.is_some() || colors.env_white.is_some() {
+            scheme.override_lights = true;
+        }
+        scheme.saber_left = colors.left.unwrap_or(scheme.saber_left);
+        scheme.saber_right = colors.right.unwrap_or(scheme.saber_right);
+        scheme.obstacle = colors.obstacle.unwrap_or(scheme.obstacle);
+        scheme.env_left_boost = colors.env_left_boost.or(colors.env_left).unwrap_or(scheme.env_left_boost);
+        scheme.env_right_boost = colors.env_right_boost.or(colors.env_right).unwrap_or(scheme.env_right_boost);
+        scheme.env_white_boost = colors.env_white_boost.or(colors.env_white).or(scheme.env_white_boost);
+        scheme.env_left = colors.env_left.unwrap_or(scheme.env_left);
+        scheme.env_right = colors.env_right.unwrap_or(scheme.env_right);
+        scheme.env_white = colors.env_white.or(scheme.env_white);
+        scheme
+    }
+}
+
+impl BeatmapSetMeta {
+    /// Colors `beatmap` is played with: its v4 color scheme and SongCore overrides on top of [ColorScheme::default].
+    /// Environment specific colors aren't known, so parts a scheme doesn't override fall back to the defaults
+    pub fn color_scheme(&self, beatmap: &BeatmapMeta) -> ColorScheme {
+        let default = ColorScheme::default();
+        let mut scheme = default.clone();
+        if let Some(chosen) = beatmap.color_scheme.and_then(|x| self.color_schemes.get(x)) {
+            scheme = chosen.clone();
+            if !chosen.override_notes {
+                (scheme.saber_left, scheme.saber_right, scheme.obstacle) = (default.saber_left, default.saber_right, default.obstacle);
+            }
+            if !chosen.override_lights {
+                (scheme.env_left, scheme.env_right, scheme.env_white) = (default.env_left, default.env_right, default.env_white);
+                (scheme.env_left_boost, scheme.env_right_boost, scheme.env_white_boost) = (default.env_left_boost, default.env_right_boost, default.env_white_boost);
+            }
+        }
+        scheme.with_overrides(&beatmap.colors())
+    }
+}
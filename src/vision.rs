@@ -0,0 +1,85 @@
+//! Detection of vision blocks: objects in front of the player's face hiding what comes shortly after them
+
+use crate::parity::SWING_GROUPING;
+use crate::types::primary::{BeatmapMeta, Event};
+use crate::{Beatmap, Timing};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Blocker {
+    Note,
+    Bomb,
+    Obstacle,
+}
+
+/// An object in the center columns of the middle row, and what it hides
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisionBlock {
+    pub beat: f64,
+    pub blocker: Blocker,
+    /// Beats of the notes and bombs in the lanes behind the blocker, spawning while it's in front of them
+    pub hidden_beats: Vec<f64>,
+    /// Longest time, in seconds, any of them stays hidden
+    pub hidden_for: f64,
+}
+
+fn blocks_sight(x: i32, y: i32) -> bool {
+    (x == 1 || x == 2) && y == 1
+}
+
+/// Finds the objects blocking the view of notes and bombs that spawn behind them before they pass the player. Walls block until their end passes.
+/// Objects closer than [SWING_GROUPING] seconds to the blocker are seen along with it. `bpm` is the one the jump is based on, the level's
+pub fn vision_blocks(beatmap: &Beatmap, meta: &BeatmapMeta, bpm: f64, timing: &Timing) -> Vec<VisionBlock> {
+    let jump_seconds = meta.half_jump_duration(bpm) * 60.0 / bpm;
+    // Start and end beat, and the lanes covered, from the first to past the last
+    let mut blockers: Vec<(f64, f64, Blocker, f64, f64)> = vec![];
+    let mut targets: Vec<(f64, i32)> = vec![];
+    for event in &beatmap.events {
+        match event {
+            Event::Note(note) => {
+                targets.push((note.beat, note.x));
+                if blocks_sight(note.x, note.y) {
+                    blockers.push((note.beat, note.beat, Blocker::Note, note.x as f64, note.x as f64 + 1.0));
+                }
+            },
+            Event::Bomb(bomb) => {
+                targets.push((bomb.beat, bomb.x));
+                if blocks_sight(bomb.x, bomb.y) {
+                    blockers.push((bomb.beat, bomb.beat, Blocker::Bomb, bomb.x as f64, bomb.x as f64 + 1.0));
+                }
+            },
+            Event::Obstacle(obstacle) => {
+                let covers_center = obstacle.x < 3 && obstacle.x as f64 + obstacle.width > 1.0;
+                let covers_middle = obstacle.y <= 1 && obstacle.y as f64 + obstacle.height > 1.0;
+                if covers_center && covers_middle {
+                    let end = obstacle.beat + obstacle.duration.max(0.0);
+                    blockers.push((obstacle.beat, end, Blocker::Obstacle, obstacle.x as f64, obstacle.x as f64 + obstacle.width));
+                }
+            },
+            _ => {}
+        }
+    }
+    blockers.sort_by(|a, b| a.0.total_cmp(&b.0));
+    targets.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut blocks = vec![];
+    for (beat, end, blocker, from_lane, to_lane) in blockers {
+        let (start_seconds, end_seconds) = (timing.beat_to_seconds(beat), timing.beat_to_seconds(end));
+        let mut block = VisionBlock { beat, blocker, hidden_beats: vec![], hidden_for: 0.0 };
+        for (target, x) in targets.iter().filter(|x| x.0 > beat) {
+            let seconds = timing.beat_to_seconds(*target);
+            if seconds - end_seconds >= jump_seconds {
+                break;
+            }
+            let behind = *x as f64 >= from_lane && (*x as f64) < to_lane;
+            if behind && seconds - start_seconds > SWING_GROUPING {
+                // Hidden from spawning until the blocker passes the player, or until it arrives itself
+                block.hidden_beats.push(*target);
+                block.hidden_for = block.hidden_for.max(end_seconds.min(seconds) - (seconds - jump_seconds));
+            }
+        }
+        if !block.hidden_beats.is_empty() {
+            blocks.push(block);
+        }
+    }
+    blocks
+}